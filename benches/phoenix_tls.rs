@@ -24,7 +24,9 @@ phoenix_tls::phoenix_tls! {
 fn with(b: &mut Bencher) {
     b.iter(|| {
         for _ in 0..1_000_000 {
-            drop(VAL.with(|_| {}))
+            VAL.with(|x| {
+                test::black_box(x);
+            })
         }
     })
 }
@@ -37,3 +39,15 @@ fn get(b: &mut Bencher) {
         }
     })
 }
+
+#[bench]
+fn pinned(b: &mut Bencher) {
+    let pinned = VAL.pin();
+    b.iter(|| {
+        for _ in 0..1_000_000 {
+            pinned.with(|x| {
+                test::black_box(x);
+            })
+        }
+    })
+}
@@ -1,37 +1,42 @@
-use phoenix_tls::{phoenix_tls, PhoenixTarget};
+use phoenix_tls::{phoenix_tls, PhoenixId, PhoenixTarget};
 use std::sync::{
     atomic::{AtomicUsize, Ordering::Relaxed},
     Mutex,
 };
 
 lazy_static::lazy_static! {
-    static ref THREAD_LIST: Mutex<Vec<usize>> = Default::default();
+    static ref THREAD_LIST: Mutex<Vec<PhoenixId<Thread>>> = Default::default();
 }
 
 static THREAD_ID: AtomicUsize = AtomicUsize::new(0);
 
 struct Thread {
-    id: usize,
+    id:         usize,
+    generation: u32,
 }
 
 impl Default for Thread {
     fn default() -> Self {
         let id = THREAD_ID.fetch_add(1, Relaxed);
-        Self { id }
+        Self { id, generation: 0 }
     }
 }
 
 impl PhoenixTarget for Thread {
-    fn subscribe(&mut self) {
-        THREAD_LIST.lock().unwrap().push(self as *const _ as usize)
+    fn subscribe(&mut self) {}
+
+    fn subscribe_with_index(&mut self, index: u64) {
+        self.generation = index as u32;
+        THREAD_LIST
+            .lock()
+            .unwrap()
+            .push(PhoenixId::of(self, self.generation))
     }
 
     fn unsubscribe(&mut self) {
         let mut list = THREAD_LIST.lock().unwrap();
-        let position = list
-            .iter()
-            .position(|x| *x == self as *const _ as usize)
-            .unwrap();
+        let id = PhoenixId::of(self, self.generation);
+        let position = list.iter().position(|x| *x == id).unwrap();
         list.remove(position);
     }
 }
@@ -0,0 +1,60 @@
+use phoenix_tls::{phoenix_tls, PhoenixCell, PhoenixTarget};
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+// per job scratch state, which shouldn't leak from one job into the next
+#[derive(Default)]
+struct Scratch {
+    visited: PhoenixCell<usize>,
+}
+
+impl PhoenixTarget for Scratch {
+    fn subscribe(&mut self) {
+        println!("{:?}: fresh scratch", thread::current().id());
+    }
+
+    fn unsubscribe(&mut self) {
+        println!(
+            "{:?}: scratch visited {}",
+            thread::current().id(),
+            self.visited.get()
+        );
+    }
+}
+
+phoenix_tls! {
+    static SCRATCH: Scratch;
+}
+
+fn main() {
+    let (sender, receiver) = mpsc::channel::<usize>();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            let receiver = receiver.clone();
+            thread::spawn(move || loop {
+                let job = match receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+                SCRATCH.with_scope(|| {
+                    for _ in 0..job {
+                        SCRATCH.with(|scratch| scratch.visited.update(|n| n + 1));
+                    }
+                    assert_eq!(job, SCRATCH.with(|scratch| scratch.visited.get()));
+                })
+            })
+        })
+        .collect();
+
+    for job in 1..=4 {
+        sender.send(job).unwrap();
+    }
+    drop(sender);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+use phoenix_tls::{phoenix_tls, PhoenixTarget, ThreadRegistry};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+static THREAD_LIST: ThreadRegistry<Thread> = ThreadRegistry::new();
+
+static THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+struct Thread {
+    id: usize,
+}
+
+impl Default for Thread {
+    fn default() -> Self {
+        let id = THREAD_ID.fetch_add(1, Relaxed);
+        Self { id }
+    }
+}
+
+impl PhoenixTarget for Thread {
+    fn subscribe(&mut self) {}
+
+    fn unsubscribe(&mut self) {}
+
+    // registers each value after `subscribe`, and deregisters it before `unsubscribe` borrows it
+    // mutably, so `for_each` on another thread never reads a value that is being torn down
+    fn live_registry() -> Option<&'static ThreadRegistry<Self>> {
+        Some(&THREAD_LIST)
+    }
+}
+
+phoenix_tls! {
+    static THREAD: Thread;
+}
+
+fn main() {
+    let main_id = THREAD.handle().id;
+    println!("main id: {}", main_id);
+
+    assert_eq!(main_id, THREAD.handle().id);
+    std::thread::spawn(move || {
+        println!("num threads: {}", THREAD_LIST.len());
+        let this_id = THREAD.handle().id;
+        println!("other id: {}", this_id);
+        println!("num threads: {}", THREAD_LIST.len());
+        assert_ne!(main_id, this_id);
+        assert_eq!(this_id, THREAD.handle().id);
+        THREAD_LIST.for_each(|thread| println!("live thread: {}", thread.id));
+    })
+    .join()
+    .unwrap();
+    println!("num threads: {}", THREAD_LIST.len())
+}
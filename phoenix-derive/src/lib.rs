@@ -0,0 +1,86 @@
+//! `#[derive(PhoenixTarget)]` for phoenix-tls.
+//!
+//! Without attributes, the generated `subscribe`/`unsubscribe` do nothing, like `NoSubscribe`.
+//! With `#[phoenix(registry = PATH)]`, where `PATH` names a `static` `ThreadRegistry<Self>`, the
+//! registry is returned from `live_registry`, and `Phoenix` registers every value after
+//! `subscribe` and deregisters it before `unsubscribe`. The generated code never registers
+//! anything itself, so a value that isn't inside a `Phoenix` can't end up in the registry.
+//!
+//! The generated code refers to `::phoenix_tls`. Crates that reexport it under another path can
+//! name that path with `#[phoenix(crate = PATH)]`.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Path};
+
+#[proc_macro_derive(PhoenixTarget, attributes(phoenix))]
+pub fn derive_phoenix_target(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Attrs { registry, krate } = attrs(&input)?;
+    let krate = krate.unwrap_or_else(|| syn::parse_quote!(::phoenix_tls));
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let live_registry = registry.map(|registry| {
+        quote! {
+            #[inline]
+            fn live_registry() -> ::core::option::Option<
+                &'static #krate::ThreadRegistry<Self>,
+            > {
+                ::core::option::Option::Some(&#registry)
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics #krate::PhoenixTarget for #name #ty_generics #where_clause {
+            #[inline]
+            fn subscribe(&mut self) {}
+
+            #[inline]
+            fn unsubscribe(&mut self) {}
+
+            #live_registry
+        }
+    })
+}
+
+#[derive(Default)]
+struct Attrs {
+    registry: Option<Path>,
+    krate:    Option<Path>,
+}
+
+fn attrs(input: &DeriveInput) -> syn::Result<Attrs> {
+    let mut attrs = Attrs::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("phoenix"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let (slot, name) = if meta.path.is_ident("registry") {
+                (&mut attrs.registry, "registry")
+            } else if meta.path.is_ident("crate") {
+                (&mut attrs.krate, "crate")
+            } else {
+                return Err(meta.error(
+                    "unsupported `phoenix` attribute, expected `registry = PATH` or `crate = PATH`",
+                ));
+            };
+            if slot.is_some() {
+                return Err(meta.error(format_args!("duplicate `{}` attribute", name)));
+            }
+            *slot = Some(meta.value()?.parse::<Path>()?);
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
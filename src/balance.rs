@@ -0,0 +1,46 @@
+use core::any::{self, TypeId};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+// subscribed but not yet unsubscribed values, by type
+static BALANCE: Mutex<Option<HashMap<TypeId, (&'static str, usize)>>> = Mutex::new(None);
+
+/// Returns the name and count of every `PhoenixTarget` type with values that were subscribed but
+/// not yet unsubscribed, across all threads.
+///
+/// Meant for tests to assert on once their phoenix values should be gone, e.g. after joining the
+/// threads that created them. There is no check at process exit, since the main thread's thread
+/// locals are never destroyed.
+pub fn unbalanced() -> Vec<(&'static str, usize)> {
+    let balance = BALANCE.lock().unwrap_or_else(PoisonError::into_inner);
+    balance
+        .iter()
+        .flatten()
+        .filter(|(_, (_, count))| *count != 0)
+        .map(|(_, &entry)| entry)
+        .collect()
+}
+
+#[inline(never)]
+pub(crate) fn on_subscribe<T: 'static>() {
+    let mut balance = BALANCE.lock().unwrap_or_else(PoisonError::into_inner);
+    balance
+        .get_or_insert_with(HashMap::new)
+        .entry(TypeId::of::<T>())
+        .or_insert((any::type_name::<T>(), 0))
+        .1 += 1;
+}
+
+// Always follows the `on_subscribe` of the same value, so the count can't underflow.
+#[inline(never)]
+pub(crate) fn on_unsubscribe<T: 'static>() {
+    let mut balance = BALANCE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((_, count)) = balance
+        .as_mut()
+        .and_then(|balance| balance.get_mut(&TypeId::of::<T>()))
+    {
+        *count -= 1;
+    }
+}
@@ -0,0 +1,45 @@
+/// A `phoenix_tls!` declaration, registered with the `inventory` feature.
+pub struct DeclaredKey {
+    name:      &'static str,
+    type_name: fn() -> &'static str,
+    init:      fn(),
+}
+
+inventory::collect!(DeclaredKey);
+
+impl DeclaredKey {
+    #[doc(hidden)]
+    pub const fn __new(name: &'static str, type_name: fn() -> &'static str, init: fn()) -> Self {
+        DeclaredKey {
+            name,
+            type_name,
+            init,
+        }
+    }
+
+    /// Returns the name of the declared static.
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the name of the type held by the thread local.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        (self.type_name)()
+    }
+
+    /// Initializes the thread local on the current thread, same as `PhoenixKey::init`.
+    #[inline]
+    pub fn init(&self) {
+        (self.init)()
+    }
+}
+
+/// Returns every `phoenix_tls!` declaration in the program, in no particular order.
+///
+/// Calling `init` on each one right after spawning a thread subscribes all of them eagerly.
+#[inline]
+pub fn declared_keys() -> impl Iterator<Item = &'static DeclaredKey> {
+    inventory::iter::<DeclaredKey>.into_iter()
+}
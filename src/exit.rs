@@ -0,0 +1,65 @@
+use crate::{phoenix_tls, PhoenixKey, PhoenixTarget, ResurrectionPolicy};
+use core::{cell::RefCell, mem};
+
+#[derive(Default)]
+struct ExitHooks {
+    hooks: RefCell<Vec<Box<dyn FnOnce()>>>,
+}
+
+impl PhoenixTarget for ExitHooks {
+    #[inline]
+    fn subscribe(&mut self) {}
+
+    fn unsubscribe(&mut self) {
+        for hook in mem::take(self.hooks.get_mut()) {
+            hook()
+        }
+    }
+}
+
+phoenix_tls! {
+    static RAW_EXIT_HOOKS: ExitHooks;
+}
+
+// a temporary runs its hooks as soon as it is dropped, which is what late registrations need no
+// matter which policy the crate features select
+const EXIT_HOOKS: PhoenixKey<ExitHooks> =
+    RAW_EXIT_HOOKS.with_resurrection(ResurrectionPolicy::Temporary);
+
+/// Registers `f` to run when the current thread exits.
+///
+/// Hooks run in the order they were registered, when an internal phoenix thread local is
+/// destroyed. Relative to the `unsubscribe` calls of other phoenix thread locals, they run in
+/// whatever order the standard library runs thread local destructors, which is unspecified. If the
+/// thread is already past that point, `f` runs immediately, even with `panic-on-resurrection` or
+/// `cache-resurrection`.
+pub fn on_thread_exit(f: impl FnOnce() + 'static) {
+    EXIT_HOOKS.with(|exit_hooks| exit_hooks.hooks.borrow_mut().push(Box::new(f)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn hooks_run_in_order_when_the_thread_exits() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        thread::spawn({
+            let ran = ran.clone();
+            move || {
+                for i in 0..2 {
+                    let ran = ran.clone();
+                    on_thread_exit(move || ran.lock().unwrap().push(i));
+                }
+                assert!(ran.lock().unwrap().is_empty());
+            }
+        })
+        .join()
+        .unwrap();
+        assert_eq!(*ran.lock().unwrap(), [0, 1]);
+    }
+}
@@ -0,0 +1,82 @@
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ptr::NonNull,
+};
+
+/// A typed token for the stable address of a phoenix `T`, tagged with its generation.
+///
+/// Obtain one with `PhoenixId::of(self, index as u32)` inside `subscribe_with_index`, or with
+/// `Phoenix::id`. The address is only meaningful between the matching `subscribe` and
+/// `unsubscribe`; afterwards it may be reused by another value, but that value will have a
+/// different generation. The token is never dereferenced, so it may be freely sent between
+/// threads.
+pub struct PhoenixId<T> {
+    ptr:        NonNull<T>,
+    generation: u32,
+}
+
+// the pointer is only ever compared, never dereferenced
+unsafe impl<T> Send for PhoenixId<T> {}
+unsafe impl<T> Sync for PhoenixId<T> {}
+
+impl<T> PhoenixId<T> {
+    /// Returns the id of `value`.
+    ///
+    /// `generation` should be the index passed to `subscribe_with_index`, truncated to 32 bits, so
+    /// that the id compares equal to the one returned by `Phoenix::id`.
+    #[inline]
+    pub fn of(value: &T, generation: u32) -> Self {
+        PhoenixId {
+            ptr: NonNull::from(value),
+            generation,
+        }
+    }
+
+    /// Returns the address as a raw pointer.
+    #[inline]
+    pub fn as_ptr(self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns the generation of the allocation.
+    #[inline]
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for PhoenixId<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PhoenixId<T> {}
+
+impl<T> PartialEq for PhoenixId<T> {
+    #[inline]
+    fn eq(&self, rhs: &Self) -> bool {
+        self.ptr == rhs.ptr && self.generation == rhs.generation
+    }
+}
+
+impl<T> Eq for PhoenixId<T> {}
+
+impl<T> Hash for PhoenixId<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state);
+        self.generation.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for PhoenixId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhoenixId")
+            .field("ptr", &self.ptr)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
@@ -0,0 +1,849 @@
+use crate::{Phoenix, PhoenixTarget};
+use core::{
+    cell::{Cell, RefCell},
+    mem::{self, ManuallyDrop},
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+use std::thread::{AccessError, LocalKey};
+
+static RESURRECTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns how many temporaries have been created, on any thread, because a phoenix thread local
+/// was accessed after being destroyed.
+///
+/// With `cache-resurrection`, reusing a cached temporary doesn't count.
+#[inline]
+pub fn resurrection_count() -> u64 {
+    RESURRECTIONS.load(Relaxed)
+}
+
+#[cold]
+fn run_on_initializer<F, O, T>(key: PhoenixKey<T>, f: F) -> O
+where
+    F: FnOnce(&T) -> O,
+    T: PhoenixTarget + 'static,
+{
+    let phoenix = resurrect_cached(key);
+    let o = f(&phoenix);
+    guard_resurrection(key, || drop(phoenix));
+    o
+}
+
+// With `ResurrectionPolicy::Cached`, reuses one leaked temporary per thread and key instead of
+// creating a new one on every access.
+#[cold]
+fn resurrect_cached<T: PhoenixTarget + 'static>(key: PhoenixKey<T>) -> Phoenix<T> {
+    if key.__resurrection != ResurrectionPolicy::Cached {
+        return resurrect(key);
+    }
+    let cached = key.__cache.try_with(|cache| match cache.phoenix.get() {
+        // the cache owns a leaked handle, so the allocation is still alive
+        Some(ptr) => ManuallyDrop::new(unsafe { Phoenix::from_raw(ptr) })
+            .deref()
+            .clone(),
+        None => {
+            let phoenix = resurrect(key);
+            cache.phoenix.set(Some(phoenix.clone().into_raw()));
+            phoenix
+        }
+    });
+    // the cache has no destructor, so it is only inaccessible while the thread is being torn down
+    // at a level below `thread_local!`
+    cached.unwrap_or_else(|_| resurrect(key))
+}
+
+#[cold]
+fn resurrect<T: PhoenixTarget + 'static>(key: PhoenixKey<T>) -> Phoenix<T> {
+    if key.__resurrection == ResurrectionPolicy::Panic {
+        panic!(
+            "attempted to access a destroyed phoenix thread local of type `{}`",
+            core::any::type_name::<T>()
+        )
+    }
+    guard_resurrection(key, || {
+        RESURRECTIONS.fetch_add(1, Relaxed);
+        Phoenix::resurrected((key.__init)())
+    })
+}
+
+// Runs `f`, which creates or drops a temporary, with `key` marked as resurrecting. A temporary
+// whose `subscribe` or `unsubscribe` accesses its own thread local would otherwise create another
+// temporary that does the same, forever.
+fn guard_resurrection<T: PhoenixTarget + 'static, O>(
+    key: PhoenixKey<T>,
+    f: impl FnOnce() -> O,
+) -> O {
+    let mut f = Some(f);
+    let guarded = key.__cache.try_with(|cache| {
+        let _guard = ReentrancyGuard::enter(
+            &cache.resurrecting,
+            "phoenix thread local accessed from the `subscribe` or `unsubscribe` of its own \
+             temporary",
+        );
+        (f.take().unwrap())()
+    });
+    // as in `resurrect_cached`, the cache is only inaccessible very late in thread teardown
+    guarded.unwrap_or_else(|_| (f.take().unwrap())())
+}
+
+// Sets a flag for its lifetime, panicking if it is already set. The flag is cleared on drop, so
+// unwinding out of a guarded call doesn't leave it set.
+struct ReentrancyGuard<'a>(&'a Cell<bool>);
+
+impl<'a> ReentrancyGuard<'a> {
+    #[inline]
+    fn enter(flag: &'a Cell<bool>, msg: &'static str) -> Self {
+        if flag.replace(true) {
+            panic!("{}", msg)
+        }
+        ReentrancyGuard(flag)
+    }
+}
+
+impl Drop for ReentrancyGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.set(false)
+    }
+}
+
+/// What a `PhoenixKey` does when it is accessed after its thread local has been destroyed.
+///
+/// The default is chosen by the crate features: `Panic` with `panic-on-resurrection`, otherwise
+/// `Cached` with `cache-resurrection`, otherwise `Temporary`. `PhoenixKey::with_resurrection`
+/// overrides it for a single key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResurrectionPolicy {
+    /// Creates a temporary for each access, unsubscribed once the access is done.
+    Temporary,
+    /// Creates a temporary on the first access, and leaks it for later accesses on the same thread
+    /// to reuse. It is never unsubscribed.
+    Cached,
+    /// Panics.
+    Panic,
+}
+
+impl ResurrectionPolicy {
+    /// The policy selected by the crate features.
+    pub const DEFAULT: Self = if cfg!(feature = "panic-on-resurrection") {
+        ResurrectionPolicy::Panic
+    } else if cfg!(feature = "cache-resurrection") {
+        ResurrectionPolicy::Cached
+    } else {
+        ResurrectionPolicy::Temporary
+    };
+}
+
+impl Default for ResurrectionPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+pub struct PhoenixKey<T: PhoenixTarget + 'static> {
+    #[doc(hidden)]
+    pub __get:          &'static LocalKey<PhoenixSlot<T>>,
+    #[doc(hidden)]
+    pub __init:         fn() -> T,
+    #[doc(hidden)]
+    pub __cache:        &'static LocalKey<ResurrectionCache<T>>,
+    #[doc(hidden)]
+    pub __resurrection: ResurrectionPolicy,
+}
+
+impl<T: PhoenixTarget + 'static> Clone for PhoenixKey<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: PhoenixTarget + 'static> Copy for PhoenixKey<T> {}
+
+impl<T: PhoenixTarget + 'static> PhoenixKey<T> {
+    #[inline]
+    pub fn handle(self) -> Phoenix<T> {
+        match self.try_handle() {
+            Some(phoenix) => phoenix,
+            None => resurrect_cached(self),
+        }
+    }
+
+    /// Same as `handle`, for async code that needs the value across an `.await`, which a `with`
+    /// closure can't span.
+    ///
+    /// The handle keeps this thread's value alive, but it is `!Send`, so the future holding it is
+    /// `!Send` too. That's fine on single threaded or thread-per-core runtimes, where the future
+    /// stays on this thread. Work-stealing runtimes may move the future to another thread between
+    /// polls, and won't accept it: use a `SyncPhoenix` for values that need to travel.
+    #[inline]
+    pub fn acquire(self) -> Phoenix<T> {
+        self.handle()
+    }
+
+    /// Resolves the thread local once, returning a `PinnedPhoenix` whose accesses skip the thread
+    /// local lookup.
+    #[inline]
+    pub fn pin(self) -> PinnedPhoenix<T> {
+        PinnedPhoenix {
+            phoenix: self.handle(),
+        }
+    }
+
+    /// Like `handle`, but returns `None` instead of creating a temporary if the thread local has
+    /// already been destroyed.
+    #[inline]
+    pub fn try_handle(self) -> Option<Phoenix<T>> {
+        self.try_with_phoenix(Phoenix::clone).ok()
+    }
+
+    /// Returns `n` handles, resolving the thread local and updating the reference count only once.
+    ///
+    /// Useful for fan-out, where each of `n` closures needs its own handle.
+    #[inline]
+    pub fn handles(self, n: usize) -> Vec<Phoenix<T>> {
+        match self.try_with_phoenix(|phoenix| phoenix.clone_many(n)) {
+            Ok(handles) => handles,
+            Err(_) => resurrect_cached(self).clone_many(n),
+        }
+    }
+
+    /// Calls `f` with this thread's value, creating it first if it doesn't exist yet.
+    ///
+    /// Once the thread local has been destroyed, `f` gets a temporary instead, as the key's
+    /// `ResurrectionPolicy` describes.
+    ///
+    /// The thread local stays borrowed while `f` runs, so `set`, `reset`, `take` and `scoped` on
+    /// this key panic inside `f` instead of freeing the value it is using. That borrow is the bulk
+    /// of an access: the `with` bench takes about 1.4 ns per access, against 1 ns with the borrow
+    /// removed and 0.35 ns through a `PinnedPhoenix`. Loops that access the same key many times
+    /// should `pin` it once instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from the key's own initializer or `subscribe`, or once the thread local
+    /// has been destroyed if the policy is `ResurrectionPolicy::Panic`.
+    #[inline]
+    pub fn with<F: FnOnce(&T) -> O, O>(self, f: F) -> O {
+        // `try_with` consumes its closure even when it fails, so `f` is passed through an `Option`
+        let mut f = Some(f);
+        match self.try_with(|x| (f.take().unwrap())(x)) {
+            Ok(o) => o,
+            Err(_) => run_on_initializer(self, f.take().unwrap()),
+        }
+    }
+
+    /// Like `with`, but returns an `AccessError` instead of creating a temporary if the thread
+    /// local has already been destroyed.
+    #[inline]
+    pub fn try_with<F: FnOnce(&T) -> O, O>(self, f: F) -> Result<O, AccessError> {
+        self.try_with_phoenix(|x| f(x))
+    }
+
+    /// Like `with`, but calls `default` instead of creating a temporary if the thread local has
+    /// already been destroyed.
+    ///
+    /// Lets shutdown code fall back to a sentinel without allocating or running any callbacks.
+    #[inline]
+    pub fn with_or<F: FnOnce(&T) -> O, D: FnOnce() -> O, O>(self, f: F, default: D) -> O {
+        match self.try_with(f) {
+            Ok(o) => o,
+            Err(_) => default(),
+        }
+    }
+
+    /// Calls `f` on a fresh temporary, exactly as if the thread local had already been destroyed,
+    /// without touching the thread local.
+    ///
+    /// Useful for measuring the cost of resurrection, or for exercising `on_resurrect` and
+    /// `unsubscribe_temporary` in isolation. Unlike a real resurrection, this never panics with
+    /// `panic-on-resurrection`, and never uses the `cache-resurrection` cache.
+    #[inline]
+    pub fn with_fresh<F: FnOnce(&T) -> O, O>(self, f: F) -> O {
+        f(&Phoenix::resurrected((self.__init)()))
+    }
+
+    /// Creates this thread's value now, if it doesn't exist yet, instead of on first access.
+    ///
+    /// Lets a thread pool run `subscribe` deterministically, right after spawning each thread.
+    /// This initializes the same thread local that `with` and `handle` use. Does nothing if the
+    /// thread local has already been destroyed.
+    #[inline]
+    pub fn init(self) {
+        let _ = self.try_with_phoenix(|_| ());
+    }
+
+    /// Returns `true` if the thread local has been created on this thread and not yet destroyed.
+    ///
+    /// This never creates the thread local. The result is only a snapshot: any later access on
+    /// this thread may create it.
+    #[inline]
+    pub fn is_initialized(self) -> bool {
+        self.__get
+            .try_with(|slot| match slot.phoenix.try_borrow() {
+                Ok(phoenix) => phoenix.is_some(),
+                // only ever mutably borrowed while storing or holding a value
+                Err(_) => true,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Clears this thread's value, so that the next access creates a fresh one, and returns the
+    /// old value if there was one.
+    ///
+    /// The returned handle, and any other outstanding `Phoenix` handles, keep the old value alive,
+    /// and it is only unsubscribed once they are all dropped. Use `Phoenix::try_unwrap` on it to
+    /// recover the `T` itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside `with` on the same key, or if the thread local has been
+    /// destroyed.
+    #[inline]
+    pub fn reset(self) -> Option<Phoenix<T>> {
+        // returned rather than dropped here, so the slot is released before any `unsubscribe`
+        // accesses this key
+        self.__get.with(|slot| slot.replace(None))
+    }
+
+    /// Replaces this thread's value with `value`, returning the old value if there was one.
+    ///
+    /// `value` is subscribed before the old value is released. Like `reset`, the returned handle
+    /// and any other outstanding `Phoenix` handles keep the old value alive, and it is only
+    /// unsubscribed once they are all dropped. A later `reset` clears `value`, and the next access
+    /// creates a fresh one from the initializer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside `with` on the same key, or if the thread local has been
+    /// destroyed.
+    #[inline]
+    pub fn set(self, value: T) -> Option<Phoenix<T>> {
+        let phoenix = Phoenix::from_value(value);
+        // returned rather than dropped here, so the slot is released before any `unsubscribe`
+        // accesses this key
+        self.__get.with(|slot| slot.replace(Some(phoenix)))
+    }
+
+    /// Replaces this thread's value with a fresh one from the initializer, returning the old value.
+    ///
+    /// If the thread local hasn't been created yet, it is left that way, and the fresh value is
+    /// returned instead. Like `set`, the fresh value is subscribed before the old value is
+    /// released, and the old value is only unsubscribed once the returned handle and any other
+    /// outstanding `Phoenix` handles are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside `with` on the same key, or if the thread local has been
+    /// destroyed.
+    #[inline]
+    pub fn take(self) -> Phoenix<T> {
+        let fresh = Phoenix::from_value((self.__init)());
+        // returned rather than dropped here, so the slot is released before any `unsubscribe`
+        // accesses this key
+        self.__get.with(|slot| slot.swap_initialized(fresh))
+    }
+
+    /// Replaces this thread's value with `value` until the returned guard is dropped, which
+    /// restores the previous value.
+    ///
+    /// Nested scopes restore in LIFO order as long as their guards are dropped in reverse order of
+    /// creation, which is the case for guards bound to locals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside `with` on the same key, or if the thread local has been
+    /// destroyed.
+    #[inline]
+    pub fn scoped(self, value: T) -> ScopedGuard<T> {
+        let phoenix = Phoenix::from_value(value);
+        let previous = self.__get.with(|slot| slot.replace(Some(phoenix)));
+        ScopedGuard {
+            key: self,
+            previous,
+        }
+    }
+
+    /// Runs `f` with a fresh value from the initializer, restoring the previous value afterwards.
+    ///
+    /// Lets pooled threads start every job clean, without state left over from earlier jobs.
+    ///
+    /// # Panics
+    ///
+    /// Same as `scoped`.
+    #[inline]
+    pub fn with_scope<R, F: FnOnce() -> R>(self, f: F) -> R {
+        let _guard = self.scoped((self.__init)());
+        f()
+    }
+
+    /// Returns a copy of this key that uses `policy` once the thread local has been destroyed.
+    ///
+    /// The copy shares the thread local, so it's usually declared next to a private
+    /// `phoenix_tls!`, e.g. `pub const KEY: PhoenixKey<T> = RAW.with_resurrection(Panic);`.
+    ///
+    /// There is no policy for eager initialization, since Rust has no hook that runs when a thread
+    /// starts; call `init` at the start of the thread instead.
+    #[inline]
+    pub const fn with_resurrection(self, policy: ResurrectionPolicy) -> Self {
+        PhoenixKey {
+            __resurrection: policy,
+            ..self
+        }
+    }
+
+    /// Returns the policy this key uses once the thread local has been destroyed.
+    #[inline]
+    pub fn resurrection_policy(self) -> ResurrectionPolicy {
+        self.__resurrection
+    }
+
+    /// Returns the `thread_local!` storing this key's value, for use with other thread local
+    /// utilities.
+    ///
+    /// Accessing it directly bypasses `with` and `handle`, so it never creates the value or a
+    /// temporary: `PhoenixSlot::try_with` returns `None` until the value is created, and once the
+    /// thread local is destroyed, `LocalKey::try_with` just fails.
+    #[inline]
+    pub fn local_key(self) -> &'static LocalKey<PhoenixSlot<T>> {
+        self.__get
+    }
+
+    #[inline]
+    fn try_with_phoenix<F: FnOnce(&Phoenix<T>) -> O, O>(self, f: F) -> Result<O, AccessError> {
+        self.__get.try_with(|slot| slot.with(self.__init, f))
+    }
+}
+
+/// The storage behind a `PhoenixKey`, created lazily on first access.
+///
+/// Only exposed through `PhoenixKey::local_key`.
+pub struct PhoenixSlot<T: PhoenixTarget + 'static> {
+    // Borrowed for the duration of every `with`, so `set` and `reset` can't free the value out
+    // from under the closure. The borrow costs about a third of a nanosecond per access over an
+    // unchecked slot; an `UnsafeCell` with a hand rolled borrow flag measured no faster.
+    phoenix:      RefCell<Option<Phoenix<T>>>,
+    initializing: Cell<bool>,
+}
+
+impl<T: PhoenixTarget + 'static> PhoenixSlot<T> {
+    #[doc(hidden)]
+    #[inline]
+    pub const fn new() -> Self {
+        PhoenixSlot {
+            phoenix:      RefCell::new(None),
+            initializing: Cell::new(false),
+        }
+    }
+
+    /// Calls `f` with this thread's `Phoenix`, or returns `None` if it hasn't been created yet.
+    ///
+    /// The slot doesn't know the initializer, so this never creates the value. Like
+    /// `PhoenixKey::with`, setting or resetting the key from inside `f` panics.
+    #[inline]
+    pub fn try_with<F: FnOnce(&Phoenix<T>) -> O, O>(&self, f: F) -> Option<O> {
+        self.phoenix.borrow().as_ref().map(f)
+    }
+
+    // The slot stays borrowed while `f` runs, so the value can't be replaced out from under it.
+    #[inline]
+    fn with<F: FnOnce(&Phoenix<T>) -> O, O>(&self, init: fn() -> T, f: F) -> O {
+        if let Some(phoenix) = &*self.phoenix.borrow() {
+            return f(phoenix);
+        }
+        self.with_uninitialized(init, f)
+    }
+
+    // kept out of line, so the hot path of `with` isn't recursive and inlines fully
+    #[inline(never)]
+    #[cold]
+    fn with_uninitialized<F: FnOnce(&Phoenix<T>) -> O, O>(&self, init: fn() -> T, f: F) -> O {
+        self.initialize(init);
+        self.with(init, f)
+    }
+
+    #[inline]
+    fn replace(&self, phoenix: Option<Phoenix<T>>) -> Option<Phoenix<T>> {
+        self.phoenix.replace(phoenix)
+    }
+
+    // Like `replace`, but leaves an empty slot empty, handing `phoenix` straight back.
+    #[inline]
+    fn swap_initialized(&self, phoenix: Phoenix<T>) -> Phoenix<T> {
+        match &mut *self.phoenix.borrow_mut() {
+            Some(old) => mem::replace(old, phoenix),
+            None => phoenix,
+        }
+    }
+
+    #[inline(never)]
+    #[cold]
+    fn initialize(&self, init: fn() -> T) {
+        let phoenix = {
+            // initializing again from `init` or `subscribe` would recurse forever
+            let _guard = ReentrancyGuard::enter(
+                &self.initializing,
+                "phoenix thread local accessed from its own initializer or `subscribe`",
+            );
+            Phoenix::from_value(init())
+        };
+
+        let mut slot = self.phoenix.borrow_mut();
+        if slot.is_none() {
+            *slot = Some(phoenix);
+        } else {
+            // `init` or `subscribe` set this thread local, e.g. with `PhoenixKey::set`. Keep that
+            // value, and drop ours without the slot borrowed in case `unsubscribe` accesses it.
+            drop(slot);
+            drop(phoenix);
+        }
+    }
+}
+
+/// The temporary reused after a `PhoenixKey`'s thread local is destroyed, with the
+/// `cache-resurrection` feature.
+///
+/// Has no destructor, so it stays accessible while other thread locals are being destroyed. The
+/// cached temporary is leaked, and never unsubscribed.
+#[doc(hidden)]
+pub struct ResurrectionCache<T> {
+    phoenix:      Cell<Option<NonNull<T>>>,
+    resurrecting: Cell<bool>,
+}
+
+impl<T> ResurrectionCache<T> {
+    #[doc(hidden)]
+    #[inline]
+    pub const fn new() -> Self {
+        ResurrectionCache {
+            phoenix:      Cell::new(None),
+            resurrecting: Cell::new(false),
+        }
+    }
+}
+
+/// Restores a `PhoenixKey`'s previous value when dropped. Returned by `PhoenixKey::scoped`.
+#[must_use = "the previous value is restored as soon as the guard is dropped"]
+pub struct ScopedGuard<T: 'static + PhoenixTarget> {
+    key:      PhoenixKey<T>,
+    previous: Option<Phoenix<T>>,
+}
+
+impl<T: 'static + PhoenixTarget> Drop for ScopedGuard<T> {
+    #[inline]
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        // if the thread local is already gone there is nothing to restore, and `previous` is
+        // dropped here instead. the scoped value is dropped after the slot is released, in case
+        // `unsubscribe` accesses this key.
+        let scoped = self.key.__get.try_with(|slot| slot.replace(previous));
+        drop(scoped);
+    }
+}
+
+/// A `Phoenix` resolved from a `PhoenixKey` up front, for hot loops that would otherwise pay for
+/// a thread local lookup on every access.
+#[derive(Debug)]
+pub struct PinnedPhoenix<T: 'static + PhoenixTarget> {
+    phoenix: Phoenix<T>,
+}
+
+impl<T: 'static + PhoenixTarget> PinnedPhoenix<T> {
+    /// Calls `f` with the pinned value.
+    #[inline]
+    pub fn with<F: FnOnce(&T) -> O, O>(&self, f: F) -> O {
+        f(&self.phoenix)
+    }
+
+    /// Returns the underlying `Phoenix` handle.
+    #[inline]
+    pub fn into_inner(self) -> Phoenix<T> {
+        self.phoenix
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Deref for PinnedPhoenix<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.phoenix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{phoenix_tls, PhoenixCell};
+    use std::{
+        panic,
+        sync::{
+            atomic::{AtomicUsize, Ordering::SeqCst},
+            mpsc,
+        },
+        thread,
+    };
+
+    #[derive(Default)]
+    struct Reentrant;
+
+    impl PhoenixTarget for Reentrant {
+        fn subscribe(&mut self) {
+            REENTRANT.with(|_| ())
+        }
+
+        fn unsubscribe(&mut self) {}
+    }
+
+    phoenix_tls! {
+        static REENTRANT: Reentrant;
+    }
+
+    phoenix_tls! {
+        static COUNTER: PhoenixCell<u32>;
+    }
+
+    static SUBSCRIBES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default)]
+    struct Subscribed;
+
+    impl PhoenixTarget for Subscribed {
+        fn subscribe(&mut self) {
+            SUBSCRIBES.fetch_add(1, SeqCst);
+        }
+
+        fn unsubscribe(&mut self) {}
+    }
+
+    phoenix_tls! {
+        static SUBSCRIBED: Subscribed;
+    }
+
+    phoenix_tls! {
+        static SEEDED: PhoenixCell<u32> = PhoenixCell::new(42);
+    }
+
+    // Counts the temporaries of one key, which each test declares with its own `Counts`.
+    struct Counts {
+        resurrected: AtomicUsize,
+        released:    AtomicUsize,
+    }
+
+    impl Counts {
+        const fn new() -> Self {
+            Counts {
+                resurrected: AtomicUsize::new(0),
+                released:    AtomicUsize::new(0),
+            }
+        }
+
+        fn get(&self) -> (usize, usize) {
+            (self.resurrected.load(SeqCst), self.released.load(SeqCst))
+        }
+    }
+
+    struct Tracked(&'static Counts);
+
+    impl PhoenixTarget for Tracked {
+        fn subscribe(&mut self) {}
+
+        fn on_resurrect(&mut self, _: u64) {
+            self.0.resurrected.fetch_add(1, SeqCst);
+        }
+
+        fn unsubscribe(&mut self) {}
+
+        fn unsubscribe_temporary(&mut self) {
+            self.0.released.fetch_add(1, SeqCst);
+        }
+    }
+
+    // Initializes `key` on a new thread, and returns what `f` returns when run from a thread local
+    // destructor registered before it, which on most platforms runs after `key` is destroyed.
+    fn after_teardown<T, R>(key: PhoenixKey<T>, f: impl FnOnce() -> R + Send + 'static) -> R
+    where
+        T: PhoenixTarget,
+        R: Send + 'static,
+    {
+        struct Late(RefCell<Option<Box<dyn FnOnce()>>>);
+
+        impl Drop for Late {
+            fn drop(&mut self) {
+                if let Some(f) = self.0.get_mut().take() {
+                    f()
+                }
+            }
+        }
+
+        thread_local! {
+            static LATE: Late = const { Late(RefCell::new(None)) };
+        }
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            LATE.with(|late| *late.0.borrow_mut() = Some(Box::new(move || tx.send(f()).unwrap())));
+            key.init();
+        })
+        .join()
+        .unwrap();
+        rx.recv().unwrap()
+    }
+
+    #[test]
+    fn local_key_slot_never_initializes() {
+        let slot_count = |slot: &PhoenixSlot<PhoenixCell<u32>>| slot.try_with(|c| c.get());
+        assert_eq!(COUNTER.local_key().with(slot_count), None);
+        COUNTER.with(|counter| counter.set(3));
+        assert_eq!(COUNTER.local_key().with(slot_count), Some(3));
+    }
+
+    #[test]
+    fn take_leaves_an_uninitialized_slot_alone() {
+        assert_eq!(COUNTER.take().get(), 0);
+        assert!(!COUNTER.is_initialized());
+
+        COUNTER.with(|counter| counter.set(3));
+        assert_eq!(COUNTER.take().get(), 3);
+        assert_eq!(COUNTER.with(|counter| counter.get()), 0);
+    }
+
+    #[test]
+    fn handles_share_this_threads_value_only() {
+        let a = COUNTER.handle();
+        let b = COUNTER.handle();
+        assert!(Phoenix::ptr_eq(&a, &b));
+
+        // compared while `a` is alive, so the other thread's value can't reuse its address
+        let address = Phoenix::value_ptr(&a).as_ptr() as usize;
+        let shared = thread::spawn(move || {
+            Phoenix::value_ptr(&COUNTER.handle()).as_ptr() as usize == address
+        })
+        .join()
+        .unwrap();
+        assert!(!shared);
+    }
+
+    #[test]
+    fn temporary_policy_creates_a_temporary_per_access() {
+        static COUNTS: Counts = Counts::new();
+        phoenix_tls! {
+            static TRACKED: Tracked = Tracked(&COUNTS);
+        }
+
+        let key = TRACKED.with_resurrection(ResurrectionPolicy::Temporary);
+        let initialized = after_teardown(key, move || {
+            key.with(|_| ());
+            drop(key.handle());
+            key.is_initialized()
+        });
+        assert!(!initialized);
+        assert_eq!(COUNTS.get(), (2, 2));
+    }
+
+    // the cached temporary is leaked by design, which miri reports
+    #[cfg_attr(miri, ignore)]
+    #[test]
+    fn cached_policy_reuses_one_leaked_temporary() {
+        static COUNTS: Counts = Counts::new();
+        phoenix_tls! {
+            static TRACKED: Tracked = Tracked(&COUNTS);
+        }
+
+        let key = TRACKED.with_resurrection(ResurrectionPolicy::Cached);
+        let reused = after_teardown(key, move || {
+            key.with(|_| ());
+            Phoenix::ptr_eq(&key.handle(), &key.handle())
+        });
+        assert!(reused);
+        assert_eq!(COUNTS.get(), (1, 0));
+    }
+
+    #[test]
+    fn panic_policy_panics_after_teardown() {
+        static COUNTS: Counts = Counts::new();
+        phoenix_tls! {
+            static TRACKED: Tracked = Tracked(&COUNTS);
+        }
+
+        let key = TRACKED.with_resurrection(ResurrectionPolicy::Panic);
+        let message = after_teardown(key, move || {
+            let message = panic::catch_unwind(|| key.with(|_| ())).unwrap_err();
+            *message.downcast::<String>().unwrap()
+        });
+        assert!(message.contains("attempted to access a destroyed phoenix thread local"));
+        assert!(message.contains("Tracked"));
+        assert_eq!(COUNTS.get(), (0, 0));
+    }
+
+    #[test]
+    fn first_handle_comes_from_the_initializer() {
+        assert!(!SEEDED.is_initialized());
+        assert_eq!(SEEDED.handle().get(), 42);
+    }
+
+    #[test]
+    fn try_handle_in_a_thread_local_destructor_never_resurrects() {
+        struct Guard;
+
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                drop(SUBSCRIBED.try_handle());
+            }
+        }
+
+        thread_local! {
+            static GUARD: Guard = const { Guard };
+        }
+
+        // the guard is registered first, so on most platforms it is destroyed after `SUBSCRIBED`
+        thread::spawn(|| {
+            GUARD.with(|_| ());
+            SUBSCRIBED.init();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(SUBSCRIBES.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn same_typed_statics_are_independent() {
+        static SUBSCRIBES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Default)]
+        struct Level(u32);
+
+        impl PhoenixTarget for Level {
+            fn subscribe(&mut self) {
+                SUBSCRIBES.fetch_add(1, SeqCst);
+            }
+
+            fn unsubscribe(&mut self) {}
+        }
+
+        phoenix_tls! {
+            static LOW: Level;
+            static HIGH: Level;
+        }
+
+        assert!(!Phoenix::ptr_eq(&LOW.handle(), &HIGH.handle()));
+        assert_eq!(SUBSCRIBES.load(SeqCst), 2);
+
+        drop(HIGH.set(Level(7)));
+        assert_eq!(HIGH.with(|level| level.0), 7);
+        assert_eq!(LOW.with(|level| level.0), 0);
+    }
+
+    #[test]
+    fn reentrant_subscribe_panics() {
+        let message = panic::catch_unwind(|| REENTRANT.with(|_| ()))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(message.contains("accessed from its own initializer or `subscribe`"));
+        assert!(!REENTRANT.is_initialized());
+    }
+}
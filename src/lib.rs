@@ -4,6 +4,55 @@
 //! the `thread_local` is accessed after it's destroyed, a new temporary will be created using
 //! `Default::default()`.
 //!
+//! Declarations of the form `static NAME: T = expr;` initialize the thread local, and any
+//! temporaries, with `expr` instead. Those targets don't need to implement `Default`.
+//!
+//! Every declaration is independent, even if several have the same type: each has its own value,
+//! subscribed and unsubscribed separately.
+//!
+//! `phoenix_tls_mut!` declares thread locals of plain values, without hooks, that can also be
+//! mutated while other handles exist, see `PhoenixMutKey`.
+//!
+//! Enabling the `panic-on-resurrection` feature makes accesses after destruction panic instead of
+//! creating a temporary, which helps track down accidental late accesses. Without it,
+//! `resurrection_count` reports how many temporaries have been created.
+//!
+//! With the `cache-resurrection` feature, the first temporary created on a thread is leaked and
+//! reused by every later access on that thread, instead of subscribing and unsubscribing a new one
+//! each time. The cached temporary is never unsubscribed.
+//!
+//! Both can be overridden for a single key, see `ResurrectionPolicy`.
+//!
+//! The `metrics` feature counts how many `Phoenix` values have been created and are still live,
+//! see `metrics`.
+//!
+//! The `debug-balance` feature counts subscribed values per type, and lists the types with values
+//! still subscribed through `unbalanced`.
+//!
+//! The `tracing` feature emits a trace level event after every `Phoenix` subscribes and before it
+//! unsubscribes, with its address and type name.
+//!
+//! Overflowing a reference count aborts the process. The `overflow-panic` feature panics instead,
+//! unwinding only the offending thread. The count is left unchanged, so this is sound, but code
+//! that catches the panic is presumably leaking handles and may keep doing so.
+//!
+//! The reference count checks against double frees and use after free only run in debug builds.
+//! The `hardened` feature runs them in every build, aborting the process if one fails. This costs a
+//! compare and a well predicted branch on every clone and drop of a handle.
+//!
+//! The `inventory` feature registers every `phoenix_tls!` declaration in the program, see
+//! `declared_keys`.
+//!
+//! The `tokio` feature adds `task_phoenix!`, for values scoped to a tokio task instead of a
+//! thread. Tasks can move between threads, so these hold a `SyncPhoenix`.
+//!
+//! The minimum supported Rust version is 1.79. `task_phoenix!` needs 1.83, as its keys refer to a
+//! `static` from a `const`.
+//!
+//! On `wasm32-unknown-unknown` without the `atomics` target feature, std implements
+//! `thread_local!` as a plain static, so the same API works unchanged: the single value is
+//! subscribed on first access, and never unsubscribed since the "thread" never exits.
+//!
 //! All phoenix thread locals (Phoenix) are internally reference counted heap allocated structures.
 //!
 //! Additionally the user type receives two callbacks `subscribe`/`unsubscribe`, which are invoked
@@ -11,32 +60,203 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-cfg_if::cfg_if! {
-    if #[cfg(not(feature = "std"))] {
-        extern crate alloc;
-        use alloc::boxed::Box;
-    }
+extern crate alloc;
+
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, GlobalAlloc, Layout},
+    vec::Vec,
+};
+use core::{
+    borrow::Borrow,
+    cell::Cell,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    num::NonZeroUsize,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    ptr::{self, NonNull},
+    sync::atomic::Ordering::Relaxed,
+};
+
+// Checks a reference count invariant. Only in debug builds by default; with `hardened`, in every
+// build, aborting on failure.
+macro_rules! refcount_assert {
+    ($cond:expr, $msg:literal) => {
+        if cfg!(feature = "hardened") {
+            if nudge::unlikely(!$cond) {
+                $crate::refcount_corrupted($msg)
+            }
+        } else {
+            debug_assert!($cond, $msg)
+        }
+    };
 }
 
-use core::{cell::Cell, marker::PhantomData, mem::ManuallyDrop, ops::Deref, ptr::NonNull};
+#[cfg(feature = "debug-balance")]
+mod balance;
+#[cfg(feature = "inventory")]
+mod declared;
+#[cfg(feature = "std")]
+mod exit;
+mod id;
+#[cfg(feature = "std")]
+mod key;
+mod mapped;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "std")]
+mod mutable;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(target_has_atomic = "8")]
+mod spin;
+#[cfg(target_has_atomic = "ptr")]
+mod sync;
+#[cfg(feature = "tokio")]
+mod task;
+
+#[cfg(feature = "debug-balance")]
+pub use balance::unbalanced;
+#[cfg(feature = "inventory")]
+pub use declared::{declared_keys, DeclaredKey};
+#[cfg(feature = "std")]
+pub use exit::on_thread_exit;
+pub use id::PhoenixId;
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+pub use inventory as __inventory;
+#[cfg(feature = "std")]
+pub use key::{
+    resurrection_count, PhoenixKey, PhoenixSlot, PinnedPhoenix, ResurrectionCache,
+    ResurrectionPolicy, ScopedGuard,
+};
+pub use mapped::MappedPhoenix;
+#[cfg(feature = "metrics")]
+pub use metrics::{metrics, PhoenixMetrics};
+#[cfg(feature = "std")]
+pub use mutable::{PhoenixMut, PhoenixMutKey};
+#[cfg(feature = "derive")]
+pub use phoenix_derive::PhoenixTarget;
+#[cfg(feature = "std")]
+pub use registry::{
+    InlineRegistry, IntrusiveNode, IntrusiveRegistry, RegistryFull, ShardedRegistry,
+    ThreadIdRegistry, ThreadRegistry,
+};
+#[cfg(target_has_atomic = "8")]
+pub use spin::SpinRegistry;
+#[cfg(target_has_atomic = "ptr")]
+pub use sync::SyncPhoenix;
+#[cfg(feature = "tokio")]
+pub use task::TaskPhoenixKey;
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+pub use tokio as __tokio;
 
 /// Types that can be stored in phoenix_tls's can implement this for optional callback hooks for
 /// when they are created/destroyed.
-pub trait PhoenixTarget: Default {
+///
+/// `Default` is only required to create values with `Phoenix::new`, or to declare a `phoenix_tls!`
+/// without an initializer.
+pub trait PhoenixTarget: Sized {
     /// Called when a phoenix `Self` is created.
     ///
     /// A `Self` lives at the address passed into subscribe at least until `unsubscribe` is called.
+    ///
+    /// Accessing the same `phoenix_tls!` from here, or from the `unsubscribe` of a temporary,
+    /// panics instead of creating values recursively forever.
     fn subscribe(&mut self);
 
+    /// Called instead of `subscribe` with an index unique to this phoenix `Self`.
+    ///
+    /// Indices are assigned from a single counter shared by every type, so they are increasing but
+    /// not dense for any one type. On targets without 64 bit atomics the counter is a `usize`, and
+    /// wraps, and on targets without atomic read-modify-write, values created concurrently may
+    /// share an index. Defaults to calling `subscribe`.
+    #[inline]
+    fn subscribe_with_index(&mut self, index: u64) {
+        let _ = index;
+        self.subscribe()
+    }
+
+    /// Called instead of `subscribe_with_index` when a phoenix `Self` is a temporary, created
+    /// because its thread local was accessed after being destroyed.
+    ///
+    /// Temporaries are usually short-lived, so registries may want to skip them. Defaults to
+    /// calling `subscribe_with_index`.
+    #[inline]
+    fn on_resurrect(&mut self, index: u64) {
+        self.subscribe_with_index(index)
+    }
+
     /// Called when a phoenix `Self` is about to be dropped (usually at thread exit).
     ///
     /// Called with an address that was previously passed into `subscribe`.
+    ///
+    /// If this panics when the last handle is dropped, the panic is reported by the panic hook and
+    /// then swallowed, and the value is still dropped. Without the `std` feature the process
+    /// aborts instead.
     fn unsubscribe(&mut self);
+
+    /// Called instead of `unsubscribe` when a phoenix `Self` created with `on_resurrect` is about
+    /// to be dropped.
+    ///
+    /// Lets targets that skip temporaries in `on_resurrect` skip them here too. Defaults to calling
+    /// `unsubscribe`.
+    #[inline]
+    fn unsubscribe_temporary(&mut self) {
+        self.unsubscribe()
+    }
+
+    /// Called right after `unsubscribe` when the last handle is dropped, just before the value is
+    /// dropped in place.
+    ///
+    /// Lets a target consume its final state, e.g. to send it somewhere at thread exit, with
+    /// `mem::take` if it is `Unpin`. The value may have been pinned with `Phoenix::pin`, so it is
+    /// only passed as a `Pin`. Panics are handled like those from `unsubscribe`. Not called by
+    /// `Phoenix::try_unwrap`, which returns the value instead. Defaults to doing nothing.
+    #[inline]
+    fn on_drop(self: Pin<&mut Self>) {}
+
+    /// Returns a pointer to `self`.
+    ///
+    /// From `subscribe` until `unsubscribe`, this is the stable address of the value inside its
+    /// `Phoenix`, the same pointer `Phoenix::value_ptr` returns. There is no reason to override
+    /// it.
+    #[inline]
+    fn this(&self) -> NonNull<Self> {
+        NonNull::from(self)
+    }
+
+    /// Returns the allocator used for every phoenix `Self`, instead of the global allocator.
+    ///
+    /// Every allocation for a given `Self` has the same layout, so a fixed size slab or pool is
+    /// enough. Defaults to `None`, which uses the global allocator with no overhead.
+    ///
+    /// Each value is freed with the allocator returned at that point, which is why a
+    /// `PhoenixAllocator` can only be created unsafely.
+    #[inline]
+    fn allocator() -> Option<PhoenixAllocator> {
+        None
+    }
+
+    /// Returns a registry that every phoenix `Self` is added to after `subscribe`, and removed
+    /// from before `unsubscribe`.
+    ///
+    /// Defaults to `None`. Returning a registry enables `Phoenix::for_each_live`.
+    #[cfg(feature = "std")]
+    #[inline]
+    fn live_registry() -> Option<&'static ThreadRegistry<Self>> {
+        None
+    }
 }
 
 #[derive(Default)]
 pub struct NoSubscribe<T: ?Sized>(pub T);
-impl<T: ?Sized + Default> PhoenixTarget for NoSubscribe<T> {
+impl<T> PhoenixTarget for NoSubscribe<T> {
     #[inline]
     fn subscribe(&mut self) {}
 
@@ -53,37 +273,260 @@ impl<T: ?Sized> Deref for NoSubscribe<T> {
     }
 }
 
+impl<T: ?Sized> DerefMut for NoSubscribe<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// A `PhoenixTarget` with no hooks, holding a `Cell`.
+///
+/// Handy for per-thread counters and accumulators, e.g. a `phoenix_tls!` static `COUNT` of type
+/// `PhoenixCell<u64>`, incremented with `COUNT.with(|count| count.update(|n| n + 1))`.
+#[derive(Default)]
+pub struct PhoenixCell<T>(Cell<T>);
+
+impl<T> PhoenixCell<T> {
+    /// Creates a new `PhoenixCell` holding `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        PhoenixCell(Cell::new(value))
+    }
+
+    /// Sets the contained value.
+    #[inline]
+    pub fn set(&self, value: T) {
+        self.0.set(value)
+    }
+
+    /// Replaces the contained value, returning the old one.
+    #[inline]
+    pub fn replace(&self, value: T) -> T {
+        self.0.replace(value)
+    }
+}
+
+impl<T: Copy> PhoenixCell<T> {
+    /// Returns a copy of the contained value.
+    #[inline]
+    pub fn get(&self) -> T {
+        self.0.get()
+    }
+
+    /// Replaces the contained value with `f` applied to it, returning the new value.
+    #[inline]
+    pub fn update<F: FnOnce(T) -> T>(&self, f: F) -> T {
+        let value = f(self.0.get());
+        self.0.set(value);
+        value
+    }
+}
+
+impl<T> PhoenixTarget for PhoenixCell<T> {
+    #[inline]
+    fn subscribe(&mut self) {}
+
+    #[inline]
+    fn unsubscribe(&mut self) {}
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for PhoenixCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PhoenixCell").field(&self.get()).finish()
+    }
+}
+
+/// An optional `PhoenixTarget`, whose hooks are forwarded to the contained value when present.
+///
+/// There is deliberately no `DerefMut`: swapping the value between `subscribe` and `unsubscribe`
+/// would unsubscribe a value that was never subscribed.
+pub struct Optional<T>(pub Option<T>);
+
+impl<T> Default for Optional<T> {
+    #[inline]
+    fn default() -> Self {
+        Optional(None)
+    }
+}
+
+impl<T> From<Option<T>> for Optional<T> {
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        Optional(value)
+    }
+}
+
+impl<T: PhoenixTarget> PhoenixTarget for Optional<T> {
+    #[inline]
+    fn subscribe(&mut self) {
+        if let Some(value) = &mut self.0 {
+            value.subscribe()
+        }
+    }
+
+    #[inline]
+    fn subscribe_with_index(&mut self, index: u64) {
+        if let Some(value) = &mut self.0 {
+            value.subscribe_with_index(index)
+        }
+    }
+
+    #[inline]
+    fn on_resurrect(&mut self, index: u64) {
+        if let Some(value) = &mut self.0 {
+            value.on_resurrect(index)
+        }
+    }
+
+    #[inline]
+    fn unsubscribe(&mut self) {
+        if let Some(value) = &mut self.0 {
+            value.unsubscribe()
+        }
+    }
+
+    #[inline]
+    fn unsubscribe_temporary(&mut self) {
+        if let Some(value) = &mut self.0 {
+            value.unsubscribe_temporary()
+        }
+    }
+
+    #[inline]
+    fn on_drop(self: Pin<&mut Self>) {
+        // `Optional` never moves its value out on its own, so pinning is structural
+        if let Some(value) = unsafe { &mut self.get_unchecked_mut().0 } {
+            unsafe { Pin::new_unchecked(value) }.on_drop()
+        }
+    }
+}
+
+impl<T> Deref for Optional<T> {
+    type Target = Option<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 #[derive(Debug)]
 #[repr(C)]
 struct PhoenixImpl<T> {
-    value:     T,
-    ref_count: Cell<usize>,
+    value:      ManuallyDrop<T>,
+    // Number of `Phoenix`es. Nonzero for as long as `value` is alive; it only reaches zero when
+    // `value` is dropped, after which `WeakPhoenix`es may still observe it.
+    ref_count:  Cell<usize>,
+    // Number of `WeakPhoenix`es, plus one shared by all of the `Phoenix`es.
+    weak_count: Cell<usize>,
+    // The index passed to `subscribe_with_index`, truncated.
+    generation: u32,
+    // Whether `value` is a temporary, created with `on_resurrect`. Fits in the padding after
+    // `generation` on 64 bit targets.
+    temporary:  bool,
 }
 
-#[derive(Debug)]
+impl<T> PhoenixImpl<T> {
+    // Evaluated for each `T` that relies on them, failing the build if the layout ever changes:
+    // - `Phoenix::into_raw`/`from_raw` cast between pointers to a `PhoenixImpl` and to its `value`
+    // - allocating a zero sized layout is undefined behavior, even for a zero sized `T`
+    const LAYOUT_CHECKS: () = {
+        assert!(mem::offset_of!(PhoenixImpl<T>, value) == 0);
+        assert!(mem::size_of::<PhoenixImpl<T>>() >= mem::size_of::<Cell<usize>>());
+    };
+}
+
+impl<T: PhoenixTarget> PhoenixImpl<T> {
+    // The hook matching the one `value` was subscribed with.
+    #[inline]
+    fn unsubscribe_hook(&self) -> fn(&mut T) {
+        if self.temporary {
+            T::unsubscribe_temporary
+        } else {
+            T::unsubscribe
+        }
+    }
+}
+
+// also checked once here, so a layout change fails the crate's own build
+const _: () = PhoenixImpl::<()>::LAYOUT_CHECKS;
+
+// the `temporary` flag only uses padding on 64 bit targets
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(mem::size_of::<PhoenixImpl<usize>>() == 4 * mem::size_of::<usize>());
+
+/// A reference counted handle to a phoenix `T`, analogous to `std::rc::Rc`.
+///
+/// Like `Rc`, a `Phoenix` is covariant in `T`, and is neither `Send` nor `Sync`. Also like `Rc`,
+/// functions with names that targets are likely to use too are associated functions, e.g.
+/// `Phoenix::id(&x)`, so they never shadow methods of `T` reached through `Deref`.
 pub struct Phoenix<T: 'static + PhoenixTarget> {
     raw:     NonNull<PhoenixImpl<T>>,
+    // Covariant in `T`, and `!Send`/`!Sync` through `NonNull` and the `Cell`s. Drop check needs
+    // nothing more: `Drop` is implemented without `#[may_dangle]`, so `T` has to outlive every
+    // `Phoenix` regardless, and it is `'static` besides.
     phantom: PhantomData<PhoenixImpl<T>>,
 }
 
+// Fails to compile if `Phoenix` or `WeakPhoenix` ever become `Send` or `Sync`, which would be
+// unsound with non-atomic reference counts. A second impl would then apply, making the calls
+// ambiguous.
+const _: () = {
+    trait AmbiguousIfSend<A> {
+        fn check() {}
+    }
+    impl<T: ?Sized> AmbiguousIfSend<()> for T {}
+    impl<T: ?Sized + Send> AmbiguousIfSend<u8> for T {}
+
+    trait AmbiguousIfSync<A> {
+        fn check() {}
+    }
+    impl<T: ?Sized> AmbiguousIfSync<()> for T {}
+    impl<T: ?Sized + Sync> AmbiguousIfSync<u8> for T {}
+
+    #[allow(dead_code)]
+    fn not_send_or_sync() {
+        type Target = NoSubscribe<()>;
+        <Phoenix<Target> as AmbiguousIfSend<_>>::check();
+        <Phoenix<Target> as AmbiguousIfSync<_>>::check();
+        <WeakPhoenix<Target> as AmbiguousIfSend<_>>::check();
+        <WeakPhoenix<Target> as AmbiguousIfSync<_>>::check();
+    }
+};
+
+// Fails to compile if `Phoenix` or `WeakPhoenix` ever stop being covariant. `T: 'static` rules out
+// shortening lifetimes directly, so this relies on higher ranked fn pointers being subtypes of
+// their instantiations.
+const _: () = {
+    type Sub = NoSubscribe<for<'a> fn(&'a ())>;
+    type Super = NoSubscribe<fn(&'static ())>;
+
+    #[allow(dead_code)]
+    fn phoenix(x: Phoenix<Sub>) -> Phoenix<Super> {
+        x
+    }
+
+    #[allow(dead_code)]
+    fn weak(x: WeakPhoenix<Sub>) -> WeakPhoenix<Super> {
+        x
+    }
+};
+
 impl<T: 'static + PhoenixTarget> Clone for Phoenix<T> {
     #[inline]
     fn clone(&self) -> Self {
-        let count = self.as_ref().ref_count.get();
-        debug_assert!(count > 0, "attempt to clone a deallocated `Phoenix`");
-
-        let new_count = count.wrapping_add(1);
-        self.as_ref().ref_count.set(new_count);
-
-        // We must check for overflow because users can mem::forget(x.clone())
-        // repeatedly.
-        if nudge::unlikely(new_count == 0) {
-            nudge::abort()
+        match self.clone_checked() {
+            Ok(phoenix) => phoenix,
+            Err(CloneOverflow) => refcount_overflow(),
         }
+    }
 
-        Phoenix {
-            raw:     self.raw,
-            phantom: PhantomData,
+    #[inline]
+    fn clone_from(&mut self, source: &Self) {
+        // already the same allocation, so the counts would end up unchanged
+        if !Phoenix::ptr_eq(self, source) {
+            *self = source.clone()
         }
     }
 }
@@ -91,11 +534,12 @@ impl<T: 'static + PhoenixTarget> Clone for Phoenix<T> {
 impl<T: 'static + PhoenixTarget> Drop for Phoenix<T> {
     #[inline]
     fn drop(&mut self) {
-        let count = self.as_ref().ref_count.get();
-        debug_assert!(count > 0, "double free on `Phoenix` attempted");
-        self.as_ref().ref_count.set(count - 1);
+        let count = self.inner().ref_count.get();
+        refcount_assert!(count > 0, "double free on `Phoenix` attempted");
+        self.inner().ref_count.set(count - 1);
 
         if nudge::unlikely(count == 1) {
+            debug_assert_not_iterating::<T>();
             // this is safe as long as the reference counting logic is safe
             unsafe {
                 dealloc::<_>(self.raw);
@@ -106,9 +550,21 @@ impl<T: 'static + PhoenixTarget> Drop for Phoenix<T> {
             unsafe extern "C" fn dealloc<T: 'static + PhoenixTarget>(
                 this_ptr: NonNull<PhoenixImpl<T>>,
             ) {
-                let mut this = Box::from_raw(this_ptr.as_ptr());
+                let unsubscribe = (*this_ptr.as_ptr()).unsubscribe_hook();
+                let value = ptr::addr_of_mut!((*this_ptr.as_ptr()).value);
+                unsubscribe_value(value.cast::<T>(), |value| {
+                    catch_in_dealloc(|| unsubscribe(value))
+                });
+                let value = &mut *value;
+                // the value is dropped in place, so it never moves again
+                catch_in_dealloc(|| T::on_drop(Pin::new_unchecked(value)));
+                catch_in_dealloc(|| ManuallyDrop::drop(value));
 
-                this.value.unsubscribe();
+                // release the weak reference shared by all the strong references
+                drop(WeakPhoenix {
+                    raw:     this_ptr,
+                    phantom: PhantomData,
+                });
             }
         }
     }
@@ -117,106 +573,1586 @@ impl<T: 'static + PhoenixTarget> Drop for Phoenix<T> {
 #[doc(hidden)]
 impl<T: 'static + PhoenixTarget> Phoenix<T> {
     #[cold]
-    pub fn new() -> Self {
-        let mut phoenix = Box::new(PhoenixImpl {
-            value:     T::default(),
-            ref_count: Cell::new(1),
-        });
-        phoenix.value.subscribe();
-        let raw = unsafe { NonNull::new_unchecked(Box::into_raw(phoenix)) };
-        Phoenix {
-            raw,
+    pub fn new() -> Self
+    where
+        T: Default,
+    {
+        Self::from_value(T::default())
+    }
+
+    #[inline]
+    fn inner(&self) -> &PhoenixImpl<T> {
+        // this is safe as long as the reference counting logic is safe
+        unsafe { self.raw.as_ref() }
+    }
+
+    // Lets tests reach counts that would take too long to reach by cloning.
+    #[cfg(all(test, feature = "std"))]
+    fn set_strong_count(this: &Self, count: usize) {
+        this.inner().ref_count.set(count)
+    }
+
+    // Returns `n` clones, with a single overflow checked increment of the count.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub(crate) fn clone_many(&self, n: usize) -> Vec<Self> {
+        let count = self.inner().ref_count.get();
+        let new_count = match count.checked_add(n) {
+            Some(new_count) => new_count,
+            None => refcount_overflow(),
+        };
+        // allocated before the count changes, so a failed allocation can't leak references
+        let mut handles = Vec::with_capacity(n);
+        self.inner().ref_count.set(new_count);
+        handles.extend((0..n).map(|_| Phoenix {
+            raw:     self.raw,
             phantom: PhantomData,
+        }));
+        handles
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Phoenix<T> {
+    /// Creates a new `Phoenix`, returning an error instead of aborting if the allocation fails.
+    ///
+    /// `subscribe` is only called once the allocation has succeeded.
+    #[cold]
+    pub fn try_new() -> Result<Self, AllocError>
+    where
+        T: Default,
+    {
+        Self::try_from_value(T::default())
+    }
+
+    /// Creates a new `Phoenix` holding the result of `f`, calling `subscribe` on it.
+    #[cold]
+    pub fn with_initializer<F: FnOnce() -> T>(f: F) -> Self {
+        Self::from_value(f())
+    }
+
+    /// Creates a new `Phoenix` holding `value`, calling `subscribe` on it.
+    #[cold]
+    pub fn from_value(value: T) -> Self {
+        match Self::try_from_value(value) {
+            Ok(phoenix) => phoenix,
+            Err(AllocError) => handle_alloc_error(Layout::new::<PhoenixImpl<T>>()),
         }
     }
 
-    #[inline]
-    unsafe fn clone_raw(raw: NonNull<T>) -> Self {
-        let result = ManuallyDrop::new(Phoenix {
-            raw:     raw.cast::<PhoenixImpl<T>>(),
+    /// Creates a new `Phoenix` holding `value`, returning `n` handles to it.
+    ///
+    /// The reference count is set to `n` once, instead of being incremented for each clone, for
+    /// fanning a new value out to several owners.
+    #[cold]
+    pub fn from_value_many(value: T, n: NonZeroUsize) -> Vec<Self> {
+        // allocated before subscribing, so a failed allocation never needs an `unsubscribe`
+        let mut handles = Vec::with_capacity(n.get());
+        let phoenix = Self::from_value(value);
+        // this is the only handle, so nothing else can observe the count changing
+        phoenix.inner().ref_count.set(n.get());
+        handles.extend((1..n.get()).map(|_| Phoenix {
+            raw:     phoenix.raw,
             phantom: PhantomData,
-        });
-        (*result).clone()
+        }));
+        handles.push(phoenix);
+        handles
+    }
+
+    /// Creates a new pinned `Phoenix` holding `value`, calling `subscribe` on it.
+    ///
+    /// The value already stays at one address from `subscribe` until `unsubscribe`, so this is
+    /// the place for self-referential targets to set up their internal pointers. Pinning
+    /// additionally guarantees that the value is never moved out again, since `try_unwrap` and
+    /// `get_mut` can't be reached through a `Pin`. Every clone of the result is pinned as well.
+    ///
+    /// There is no way to pin a thread local's value, as unpinned handles from `PhoenixKey::handle`
+    /// could move it.
+    #[cold]
+    pub fn pin(value: T) -> Pin<Self> {
+        // the only handle is pinned, and pinned handles can't move the value out
+        unsafe { Pin::new_unchecked(Self::from_value(value)) }
     }
 
     #[inline]
-    fn as_ref(&self) -> &PhoenixImpl<T> {
-        // this is safe as long as the reference counting logic is safe
-        unsafe { self.raw.as_ref() }
+    fn try_from_value(value: T) -> Result<Self, AllocError> {
+        Self::try_create(value, false)
     }
-}
 
-impl<T: 'static + PhoenixTarget> Deref for Phoenix<T> {
-    type Target = T;
+    // Creates a temporary for a thread local that has already been destroyed.
+    #[cfg(feature = "std")]
+    #[cold]
+    pub(crate) fn resurrected(value: T) -> Self {
+        match Self::try_create(value, true) {
+            Ok(phoenix) => phoenix,
+            Err(AllocError) => handle_alloc_error(Layout::new::<PhoenixImpl<T>>()),
+        }
+    }
 
     #[inline]
-    fn deref(&self) -> &T {
-        &self.as_ref().value
+    fn try_create(value: T, temporary: bool) -> Result<Self, AllocError> {
+        debug_assert_not_iterating::<T>();
+        let index = next_index();
+        // `PhoenixImpl` is never zero sized, thanks to the reference counts
+        let () = PhoenixImpl::<T>::LAYOUT_CHECKS;
+        let raw = NonNull::new(unsafe { allocate::<T, PhoenixImpl<T>>() }).ok_or(AllocError)?;
+        unsafe {
+            raw.as_ptr().write(PhoenixImpl {
+                value: ManuallyDrop::new(value),
+                ref_count: Cell::new(1),
+                weak_count: Cell::new(1),
+                generation: index as u32,
+                temporary,
+            });
+            let value = &mut (*raw.as_ptr()).value;
+            let free_on_unwind = FreeOnUnwind::<T, _>(raw, PhantomData);
+            if temporary {
+                value.on_resurrect(index)
+            } else {
+                value.subscribe_with_index(index)
+            }
+            mem::forget(free_on_unwind);
+            on_subscribed(&**value);
+        }
+        Ok(Phoenix {
+            raw,
+            phantom: PhantomData,
+        })
     }
-}
 
-#[cold]
-fn run_on_default<F, O, T>(f: F) -> O
-where
-    F: FnOnce(&T) -> O,
-    T: Default + PhoenixTarget + 'static,
-{
-    f(&*Phoenix::<T>::new())
-}
+    /// Calls `f` on every live phoenix `T`, across all threads.
+    ///
+    /// Does nothing unless `T::live_registry` returns a registry. The registry stays locked while
+    /// `f` runs, so no instance can be unsubscribed or freed mid-iteration. This makes it suitable
+    /// for broadcasts like flushing every thread's buffer at shutdown, through whatever interior
+    /// mutability `T` provides.
+    ///
+    /// `f` must not create or destroy phoenix `T`s itself, as that would deadlock. With debug
+    /// assertions on, doing so panics instead, before the value is created or destroyed. A handle
+    /// whose drop panics this way leaks its value.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn for_each_live(f: impl FnMut(&T)) {
+        if let Some(registry) = T::live_registry() {
+            registry.for_each(f)
+        }
+    }
 
-pub struct PhoenixKey<T: PhoenixTarget + 'static> {
-    #[doc(hidden)]
-    pub __get: &'static std::thread::LocalKey<Phoenix<T>>,
-}
+    /// Maps every live phoenix `T`, across all threads, through `f` and collects the results.
+    ///
+    /// Like `for_each_live`, this is empty unless `T::live_registry` returns a registry, and the
+    /// registry is locked while `f` runs. Each instance is read separately, so the snapshots are
+    /// not atomic across instances.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn collect_snapshots<S>(mut f: impl FnMut(&T) -> S) -> Vec<S> {
+        let mut snapshots = Vec::new();
+        Self::for_each_live(|value| snapshots.push(f(value)));
+        snapshots
+    }
 
-impl<T: PhoenixTarget + 'static> Clone for PhoenixKey<T> {
+    /// Returns the `PhoenixMetrics` of `T` alone, counting both `Phoenix` and `SyncPhoenix`
+    /// values.
+    ///
+    /// These counters are kept in a map locked on every create and drop, on top of the crate-wide
+    /// atomics behind `metrics`.
+    #[cfg(all(feature = "metrics", feature = "std"))]
     #[inline]
-    fn clone(&self) -> Self {
-        *self
+    pub fn metrics() -> PhoenixMetrics {
+        metrics::metrics_of::<T>()
     }
-}
 
-impl<T: PhoenixTarget + 'static> Copy for PhoenixKey<T> {}
+    /// Clones the inner value into a new `Arc`, which can be shared with other threads and can
+    /// outlive this one.
+    ///
+    /// The clone is a plain `T`: it is not subscribed, and `unsubscribe` is never called on it.
+    /// There is no zero-copy `into_arc`, as the layout of an `Arc`'s allocation is private and
+    /// differs from `Phoenix`'s.
+    #[cfg(target_has_atomic = "ptr")]
+    #[inline]
+    pub fn to_arc(&self) -> Arc<T>
+    where
+        T: Clone,
+    {
+        Arc::new(T::clone(self))
+    }
 
-impl<T: PhoenixTarget + 'static> PhoenixKey<T> {
+    /// Returns the number of `Phoenix` handles to this allocation, including the one held by the
+    /// thread local.
+    ///
+    /// This is only a snapshot. Any clone or drop of a handle on this thread changes it.
     #[inline]
-    pub fn handle(self) -> Phoenix<T> {
-        self.with(|x| unsafe { Phoenix::clone_raw(x.into()) })
+    pub fn strong_count(&self) -> usize {
+        self.inner().ref_count.get()
     }
 
+    /// Returns the number of `WeakPhoenix` handles to this allocation.
+    ///
+    /// Like `strong_count`, this is only a snapshot.
     #[inline]
-    pub fn with<F: FnOnce(&T) -> O, O>(self, f: F) -> O {
-        match self.__get.try_with(|x| NonNull::from(&**x)).ok() {
-            Some(nn) => f(unsafe { nn.as_ref() }),
-            None => run_on_default::<_, _, T>(f),
-        }
+    pub fn weak_count(&self) -> usize {
+        // every `Phoenix` together holds one implicit weak handle
+        self.inner().weak_count.get() - 1
     }
-}
 
-#[macro_export]
-macro_rules! phoenix_tls {
-    // empty (base case for the recursion)
-    () => {};
+    /// Like `clone`, but returns `CloneOverflow` instead of aborting if the reference count would
+    /// overflow.
+    #[inline]
+    pub fn clone_checked(&self) -> Result<Self, CloneOverflow> {
+        let count = self.inner().ref_count.get();
+        refcount_assert!(count > 0, "attempt to clone a deallocated `Phoenix`");
 
-    // process multiple declarations
-    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => (
-        phoenix_tls!{
-            $(#[$attr])* $vis static $name: $t
-        }
-        phoenix_tls!($($rest)*);
-    );
+        // We must check for overflow because users can mem::forget(x.clone())
+        // repeatedly.
+        let new_count = count.checked_add(1).ok_or(CloneOverflow)?;
+        self.inner().ref_count.set(new_count);
 
-    // handle a single declaration
-    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty) => (
-        $(#[$attr])* $vis const $name: $crate::PhoenixKey<$t> = $crate::PhoenixKey {
-            __get: {
-                thread_local!{
-                    $(#[$attr])* $vis static __SLOW: $crate::Phoenix<$t> =
-                        $crate::Phoenix::new();
-                }
+        Ok(Phoenix {
+            raw:     self.raw,
+            phantom: PhantomData,
+        })
+    }
 
-                &__SLOW
-            }
-        };
-    );
+    /// Returns the inner value if this is the only `Phoenix` to the allocation, otherwise returns
+    /// the handle unchanged.
+    ///
+    /// `unsubscribe` runs before the value is returned. Any `WeakPhoenix`es will fail to upgrade
+    /// afterwards.
+    #[inline]
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if this.inner().ref_count.get() != 1 {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        this.inner().ref_count.set(0);
+        // this is safe as long as the reference counting logic is safe
+        unsafe {
+            let unsubscribe = this.inner().unsubscribe_hook();
+            let value = ptr::addr_of_mut!((*this.raw.as_ptr()).value);
+            unsubscribe_value(value.cast::<T>(), unsubscribe);
+            let value = ManuallyDrop::take(&mut *value);
+
+            // release the weak reference shared by all the strong references
+            drop(WeakPhoenix {
+                raw:     this.raw,
+                phantom: PhantomData,
+            });
+            Ok(value)
+        }
+    }
+
+    /// Returns a mutable reference to the inner value if this is the only `Phoenix` to the
+    /// allocation and there are no `WeakPhoenix`es that could upgrade to another one.
+    ///
+    /// # Safety
+    ///
+    /// The reference counts only account for handles. `subscribe` may have shared the value's
+    /// address, e.g. with a registry or through `live_registry`, and other threads may read the
+    /// value through it at any time. The caller must ensure nothing reads the value through such a
+    /// reference while the returned one is alive. The value must also not be replaced or moved out,
+    /// since it is unsubscribed at this address later on.
+    #[inline]
+    pub unsafe fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if this.is_unique() {
+            // no other handle can observe the value, and the caller rules out everything else
+            Some(&mut (*this.raw.as_ptr()).value)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the inner value, first cloning it into a new allocation if
+    /// it isn't uniquely held, like `Arc::make_mut`.
+    ///
+    /// The clone is subscribed, and `this` is detached from the old allocation. `WeakPhoenix`es
+    /// count as sharing the value, and keep pointing at the old allocation.
+    ///
+    /// # Safety
+    ///
+    /// Same as `get_mut`, for whichever allocation `this` points at afterwards. A clone's
+    /// `subscribe` may already have shared its address.
+    #[inline]
+    pub unsafe fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+    {
+        if !this.is_unique() {
+            *this = Self::from_value(T::clone(this));
+        }
+        // no other handle can observe the value, and the caller rules out everything else
+        &mut (*this.raw.as_ptr()).value
+    }
+
+    #[inline]
+    fn is_unique(&self) -> bool {
+        let inner = self.inner();
+        inner.ref_count.get() == 1 && inner.weak_count.get() == 1
+    }
+
+    /// Returns a handle to a part of the value, which keeps the whole allocation alive.
+    #[inline]
+    pub fn map<U: ?Sized, F: FnOnce(&T) -> &U>(this: Self, f: F) -> MappedPhoenix<T, U> {
+        MappedPhoenix::new(this, f)
+    }
+
+    /// Returns the generation of this allocation, the low 32 bits of the index that was passed to
+    /// `subscribe_with_index`.
+    ///
+    /// Two allocations that live at the same address, one after the other, have different
+    /// generations (until the counter wraps).
+    #[inline]
+    pub fn generation(this: &Self) -> u32 {
+        this.inner().generation
+    }
+
+    /// Returns `true` if this is a temporary, created because its thread local was accessed after
+    /// being destroyed, and subscribed with `on_resurrect`.
+    #[inline]
+    pub fn is_temporary(this: &Self) -> bool {
+        this.inner().temporary
+    }
+
+    /// Returns the `PhoenixId` of this allocation.
+    #[inline]
+    pub fn id(this: &Self) -> PhoenixId<T> {
+        PhoenixId::of(this, Phoenix::generation(this))
+    }
+
+    /// Returns a pointer to the inner value, without affecting the reference count.
+    ///
+    /// The value sits at the start of the allocation, so this is also the pointer `into_raw`
+    /// returns and `from_raw` accepts; everything past the value is private. The pointer is valid
+    /// for reads for as long as any `Phoenix` to the allocation is alive. Writing through it
+    /// requires the same guarantees as `Phoenix::get_mut`, and the value must never be moved out.
+    #[inline]
+    pub fn value_ptr(this: &Self) -> NonNull<T> {
+        this.raw.cast::<T>()
+    }
+
+    /// Consumes the `Phoenix`, returning a pointer to the inner value without decrementing the
+    /// reference count.
+    ///
+    /// The handle is leaked until it is reconstituted with `Phoenix::from_raw`.
+    #[inline]
+    pub fn into_raw(self) -> NonNull<T> {
+        let () = PhoenixImpl::<T>::LAYOUT_CHECKS;
+        let this = ManuallyDrop::new(self);
+        this.raw.cast::<T>()
+    }
+
+    /// Wraps the `Phoenix` to compare and hash it by address, instead of by value.
+    #[inline]
+    pub fn by_address(this: Self) -> PhoenixByAddress<T> {
+        PhoenixByAddress(this)
+    }
+
+    /// Consumes the `Phoenix`, returning a reference to the inner value that lives forever.
+    ///
+    /// Like `Box::leak`, this is a deliberate leak: the reference count never reaches zero, so the
+    /// value is never unsubscribed, dropped, or freed.
+    #[inline]
+    pub fn leak(this: Self) -> &'static T {
+        // the leaked handle keeps the allocation alive for the rest of the program
+        unsafe { &*this.into_raw().as_ptr() }
+    }
+
+    /// Reconstitutes a `Phoenix` from a pointer previously returned by `Phoenix::into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `Phoenix::<T>::into_raw`, and each call to `into_raw` must
+    /// be paired with exactly one call to `from_raw`. Calling `from_raw` more than once for the
+    /// same `into_raw` results in a double free.
+    #[inline]
+    pub unsafe fn from_raw(ptr: NonNull<T>) -> Self {
+        // `PhoenixImpl` is `#[repr(C)]` with `value` as its first field
+        let () = PhoenixImpl::<T>::LAYOUT_CHECKS;
+        Phoenix {
+            raw:     ptr.cast::<PhoenixImpl<T>>(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns `true` if both `Phoenix`es point to the same allocation.
+    #[inline]
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        a.raw == b.raw
+    }
+
+    /// Creates a new `WeakPhoenix` pointer to this allocation.
+    #[inline]
+    pub fn downgrade(&self) -> WeakPhoenix<T> {
+        let count = self.inner().weak_count.get();
+        let new_count = match count.checked_add(1) {
+            Some(new_count) => new_count,
+            None => refcount_overflow(),
+        };
+        self.inner().weak_count.set(new_count);
+
+        WeakPhoenix {
+            raw:     self.raw,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Deref for Phoenix<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T: 'static + PhoenixTarget + Default> Default for Phoenix<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Same as `Phoenix::from_value`, calling `subscribe` on `value`.
+///
+/// ```
+/// use phoenix_tls::{NoSubscribe, Phoenix};
+///
+/// let phoenix: Phoenix<_> = NoSubscribe(5).into();
+/// assert_eq!(phoenix.0, 5);
+/// ```
+impl<T: 'static + PhoenixTarget> From<T> for Phoenix<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::from_value(value)
+    }
+}
+
+impl<T: 'static + PhoenixTarget> AsRef<T> for Phoenix<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+// consistent with the value based `Eq` and `Hash` impls
+impl<T: 'static + PhoenixTarget> Borrow<T> for Phoenix<T> {
+    #[inline]
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: 'static + PhoenixTarget + fmt::Debug> fmt::Debug for Phoenix<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Phoenix")
+            .field("value", &**self)
+            .field("strong_count", &self.strong_count())
+            .finish()
+    }
+}
+
+impl<T: 'static + PhoenixTarget + fmt::Display> fmt::Display for Phoenix<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// Compares the inner values. Use `Phoenix::ptr_eq` to check whether two handles point to the same
+/// allocation.
+impl<T: 'static + PhoenixTarget + PartialEq> PartialEq for Phoenix<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: 'static + PhoenixTarget + Eq> Eq for Phoenix<T> {}
+
+/// Hashes the inner value, consistent with `PartialEq`.
+impl<T: 'static + PhoenixTarget + Hash> Hash for Phoenix<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+/// A `Phoenix` compared and hashed by the address of its allocation, instead of by value.
+///
+/// Two `PhoenixByAddress`es are equal only if they are handles to the same allocation, even if the
+/// values are equal, which makes them usable as map keys for values that aren't `Eq` or `Hash`.
+pub struct PhoenixByAddress<T: 'static + PhoenixTarget>(pub Phoenix<T>);
+
+impl<T: 'static + PhoenixTarget> Clone for PhoenixByAddress<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        PhoenixByAddress(self.0.clone())
+    }
+}
+
+impl<T: 'static + PhoenixTarget> PartialEq for PhoenixByAddress<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Phoenix::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Eq for PhoenixByAddress<T> {}
+
+impl<T: 'static + PhoenixTarget> Hash for PhoenixByAddress<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.raw.hash(state)
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Deref for PhoenixByAddress<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: 'static + PhoenixTarget + fmt::Debug> fmt::Debug for PhoenixByAddress<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PhoenixByAddress").field(&self.0).finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: 'static + PhoenixTarget + serde::Serialize> serde::Serialize for Phoenix<T> {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+/// Deserializes a `T` and wraps it in a new allocation with `Phoenix::from_value`, calling
+/// `subscribe` on it.
+#[cfg(feature = "serde")]
+impl<'de, T: 'static + PhoenixTarget + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for Phoenix<T>
+{
+    #[inline]
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Phoenix::from_value)
+    }
+}
+
+/// A non-owning reference to a `Phoenix` allocation, analogous to `std::rc::Weak`.
+///
+/// A `WeakPhoenix` keeps the allocation alive, but not the value inside of it. Once the last
+/// `Phoenix` is dropped, `unsubscribe` is called and the value is dropped, even if `WeakPhoenix`es
+/// remain.
+#[derive(Debug)]
+pub struct WeakPhoenix<T: 'static + PhoenixTarget> {
+    raw:     NonNull<PhoenixImpl<T>>,
+    phantom: PhantomData<PhoenixImpl<T>>,
+}
+
+impl<T: 'static + PhoenixTarget> Clone for WeakPhoenix<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let count = self.inner().weak_count.get();
+        refcount_assert!(count > 0, "attempt to clone a deallocated `WeakPhoenix`");
+
+        let new_count = match count.checked_add(1) {
+            Some(new_count) => new_count,
+            None => refcount_overflow(),
+        };
+        self.inner().weak_count.set(new_count);
+
+        WeakPhoenix {
+            raw:     self.raw,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Drop for WeakPhoenix<T> {
+    #[inline]
+    fn drop(&mut self) {
+        let count = self.inner().weak_count.get();
+        refcount_assert!(count > 0, "double free on `WeakPhoenix` attempted");
+        self.inner().weak_count.set(count - 1);
+
+        if nudge::unlikely(count == 1) {
+            // this is safe as long as the reference counting logic is safe. `value` has already
+            // been dropped by the last `Phoenix`, and `ManuallyDrop` prevents dropping it again.
+            unsafe {
+                deallocate::<T, _>(self.raw);
+            }
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget> WeakPhoenix<T> {
+    /// Attempts to upgrade to a `Phoenix`, returning `None` if the value has since been dropped.
+    #[inline]
+    pub fn upgrade(&self) -> Option<Phoenix<T>> {
+        if self.inner().ref_count.get() == 0 {
+            None
+        } else {
+            let phoenix = ManuallyDrop::new(Phoenix {
+                raw:     self.raw,
+                phantom: PhantomData,
+            });
+            Some((*phoenix).clone())
+        }
+    }
+
+    #[inline]
+    fn inner(&self) -> &PhoenixImpl<T> {
+        // this is safe as long as the reference counting logic is safe
+        unsafe { self.raw.as_ref() }
+    }
+}
+
+// Allocates an `A`, i.e. a `PhoenixImpl<T>` or a `SyncPhoenixImpl<T>`, with `T::allocator`.
+#[inline]
+unsafe fn allocate<T: PhoenixTarget, A>() -> *mut A {
+    let layout = Layout::new::<A>();
+    match T::allocator() {
+        Some(allocator) => allocator.0.alloc(layout),
+        None => alloc(layout),
+    }
+    .cast::<A>()
+}
+
+// Frees an allocation from `allocate`. Nothing in a `PhoenixImpl` or `SyncPhoenixImpl` needs
+// dropping besides `value`, which is always dropped (or moved out) separately.
+#[inline]
+unsafe fn deallocate<T: PhoenixTarget, A>(ptr: NonNull<A>) {
+    let layout = Layout::new::<A>();
+    let ptr = ptr.cast::<u8>().as_ptr();
+    match T::allocator() {
+        Some(allocator) => allocator.0.dealloc(ptr, layout),
+        None => dealloc(ptr, layout),
+    }
+}
+
+// The bookkeeping after a new `Phoenix` or `SyncPhoenix` value has subscribed. Undone by
+// `unsubscribe_value`.
+#[inline]
+#[cfg_attr(
+    not(any(feature = "std", feature = "tracing")),
+    allow(unused_variables)
+)]
+unsafe fn on_subscribed<T: 'static + PhoenixTarget>(value: &T) {
+    #[cfg(feature = "tracing")]
+    trace_event(value, "phoenix subscribed");
+    #[cfg(feature = "metrics")]
+    metrics::on_create::<T>();
+    #[cfg(feature = "debug-balance")]
+    balance::on_subscribe::<T>();
+    #[cfg(feature = "std")]
+    {
+        if let Some(registry) = T::live_registry() {
+            // deregistered in `unsubscribe_value`, before the value is dropped
+            registry.register(value);
+        }
+    }
+}
+
+// Panics if called from inside `Phoenix::for_each_live` on `T`, which would deadlock on the
+// registry. Checked before creating or destroying a value, as `dealloc` can't unwind.
+#[inline]
+fn debug_assert_not_iterating<T: 'static + PhoenixTarget>() {
+    #[cfg(feature = "std")]
+    {
+        if let Some(registry) = T::live_registry() {
+            registry.debug_assert_not_iterating()
+        }
+    }
+}
+
+// Calls `unsubscribe` on a value whose last handle is gone, and undoes `on_subscribed`.
+//
+// Takes a raw pointer, as other threads may be reading the value through `T::live_registry` until
+// it is deregistered. Only after that can it be borrowed mutably.
+#[inline]
+unsafe fn unsubscribe_value<T: 'static + PhoenixTarget>(
+    value: *mut T,
+    unsubscribe: impl FnOnce(&mut T),
+) {
+    #[cfg(feature = "std")]
+    {
+        if let Some(registry) = T::live_registry() {
+            registry.deregister(&*value);
+        }
+    }
+    #[cfg(feature = "tracing")]
+    trace_event(&*value, "phoenix unsubscribing");
+    unsubscribe(&mut *value);
+    #[cfg(feature = "metrics")]
+    metrics::on_destroy::<T>();
+    #[cfg(feature = "debug-balance")]
+    balance::on_unsubscribe::<T>();
+}
+
+// Runs a step of tearing down a value, i.e. `unsubscribe`, `on_drop` or its destructor, from the
+// `extern "C"` `dealloc`s, which can't unwind. With std a panic is caught, after the panic hook has
+// reported it, so the remaining steps still run, the allocation is still freed, and the rest of
+// thread teardown carries on. Without std the panic aborts the process.
+#[inline]
+fn catch_in_dealloc<F: FnOnce()>(f: F) {
+    #[cfg(feature = "std")]
+    {
+        let _ = std::panic::catch_unwind(core::panic::AssertUnwindSafe(f));
+    }
+    #[cfg(not(feature = "std"))]
+    f();
+}
+
+// Emits a trace level event with the address and type of a phoenix value.
+#[cfg(feature = "tracing")]
+#[inline]
+fn trace_event<T>(value: &T, message: &'static str) {
+    tracing::trace!(
+        address = ?(value as *const T),
+        type_name = core::any::type_name::<T>(),
+        "{}",
+        message
+    );
+}
+
+// Drops the value and frees the allocation if `subscribe` panics while creating a `Phoenix` or a
+// `SyncPhoenix`. `unsubscribe` is not called, as the value never finished subscribing. `A` must be
+// `#[repr(C)]` and start with a `ManuallyDrop<T>`.
+struct FreeOnUnwind<T: PhoenixTarget, A>(NonNull<A>, PhantomData<T>);
+
+impl<T: PhoenixTarget, A> Drop for FreeOnUnwind<T, A> {
+    #[cold]
+    fn drop(&mut self) {
+        // only ever dropped while unwinding out of `subscribe`, before any handle exists
+        unsafe {
+            ManuallyDrop::drop(&mut *self.0.cast::<ManuallyDrop<T>>().as_ptr());
+            deallocate::<T, A>(self.0);
+        }
+    }
+}
+
+// Aborts by default, like `Arc`. The count is never changed on overflow, so panicking instead is
+// sound; it just lets a program that is leaking handles catch the panic and keep going.
+#[cold]
+#[inline(never)]
+fn refcount_overflow() -> ! {
+    if cfg!(feature = "overflow-panic") {
+        panic!("`Phoenix` reference count overflowed")
+    } else {
+        nudge::abort()
+    }
+}
+
+// Reference counts are only corrupted by unsoundness elsewhere, so unwinding could run yet more
+// code against freed memory.
+#[cold]
+#[inline(never)]
+fn refcount_corrupted(msg: &'static str) -> ! {
+    #[cfg(feature = "std")]
+    std::eprintln!("{}", msg);
+    #[cfg(not(feature = "std"))]
+    let _ = msg;
+    nudge::abort()
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_has_atomic = "64")] {
+        static NEXT_INDEX: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+
+        #[inline]
+        fn next_index() -> u64 {
+            NEXT_INDEX.fetch_add(1, Relaxed)
+        }
+    } else if #[cfg(target_has_atomic = "ptr")] {
+        // indices wrap after `usize::MAX` values, rather than never
+        static NEXT_INDEX: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+        #[inline]
+        fn next_index() -> u64 {
+            NEXT_INDEX.fetch_add(1, Relaxed) as u64
+        }
+    } else {
+        // no read-modify-write atomics, so values created at the same time, from an interrupt
+        // handler or another core, may share an index
+        static NEXT_INDEX: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+        #[inline]
+        fn next_index() -> u64 {
+            let index = NEXT_INDEX.load(Relaxed);
+            NEXT_INDEX.store(index.wrapping_add(1), Relaxed);
+            index as u64
+        }
+    }
+}
+
+/// The allocator for every phoenix value of a type, returned from `PhoenixTarget::allocator`.
+#[derive(Clone, Copy)]
+pub struct PhoenixAllocator(&'static dyn GlobalAlloc);
+
+impl PhoenixAllocator {
+    /// Wraps `allocator`.
+    ///
+    /// # Safety
+    ///
+    /// A value is freed with whatever allocator `PhoenixTarget::allocator` returns when its last
+    /// handle is dropped, not the one it was allocated with. The `allocator` returning this must
+    /// return a `PhoenixAllocator` on every call, each able to free memory allocated by the others,
+    /// e.g. by always wrapping the same `static`.
+    #[inline]
+    pub const unsafe fn new(allocator: &'static dyn GlobalAlloc) -> Self {
+        PhoenixAllocator(allocator)
+    }
+}
+
+impl fmt::Debug for PhoenixAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhoenixAllocator").finish_non_exhaustive()
+    }
+}
+
+/// The error returned by `Phoenix::try_new` when the allocator fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+/// The error returned by `Phoenix::clone_checked` when the reference count would overflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CloneOverflow;
+
+impl fmt::Display for CloneOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("reference count overflow")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CloneOverflow {}
+
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! phoenix_tls {
+    // empty (base case for the recursion)
+    () => {};
+
+    // process multiple declarations with an initializer
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr; $($rest:tt)*) => (
+        phoenix_tls!{
+            $(#[$attr])* $vis static $name: $t = $init
+        }
+        phoenix_tls!($($rest)*);
+    );
+
+    // handle a single declaration with an initializer
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr) => (
+        phoenix_tls!{
+            @key $name; $(#[$attr])* $vis $name, $t, $init
+        }
+    );
+
+    // process multiple declarations
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => (
+        phoenix_tls!{
+            $(#[$attr])* $vis static $name: $t
+        }
+        phoenix_tls!($($rest)*);
+    );
+
+    // handle a single declaration
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty) => (
+        phoenix_tls!{
+            @key $name; $(#[$attr])* $vis $name, $t, ::core::default::Default::default()
+        }
+    );
+
+    // `$label` names the declaration in `declared_keys`
+    (@key $label:ident; $(#[$attr:meta])* $vis:vis $name:ident, $t:ty, $init:expr) => (
+        // `__SLOW` and `__CACHE` are scoped to their blocks, so every declaration gets its own
+        // thread locals, even when several share a type
+        $(#[$attr])* $vis const $name: $crate::PhoenixKey<$t> = $crate::PhoenixKey {
+            __get:          {
+                thread_local!{
+                    $(#[$attr])* $vis static __SLOW: $crate::PhoenixSlot<$t> =
+                        const { $crate::PhoenixSlot::new() };
+                }
+
+                &__SLOW
+            },
+            __init:         {
+                fn __init() -> $t {
+                    $init
+                }
+
+                __init
+            },
+            __cache:        {
+                thread_local!{
+                    static __CACHE: $crate::ResurrectionCache<$t> =
+                        const { $crate::ResurrectionCache::new() };
+                }
+
+                &__CACHE
+            },
+            __resurrection: $crate::ResurrectionPolicy::DEFAULT,
+        };
+        $crate::__declare_key!($label, $name, $t);
+    );
+}
+
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_key {
+    ($label:ident, $name:ident, $t:ty) => {
+        $crate::__inventory::submit! {
+            $crate::DeclaredKey::__new(
+                ::core::stringify!($label),
+                ::core::any::type_name::<$t>,
+                || $name.init(),
+            )
+        }
+    };
+}
+
+#[cfg(all(feature = "std", not(feature = "inventory")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_key {
+    ($label:ident, $name:ident, $t:ty) => {};
+}
+
+/// Declares task locals holding a `SyncPhoenix`, accessed through a `TaskPhoenixKey`.
+///
+/// Unlike `phoenix_tls!`, there is no initializer: each `TaskPhoenixKey::scope` provides its own
+/// value.
+#[cfg(feature = "tokio")]
+#[macro_export]
+macro_rules! task_phoenix {
+    // empty (base case for the recursion)
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty $(; $($rest:tt)*)?) => (
+        $(#[$attr])* $vis const $name: $crate::TaskPhoenixKey<$t> = $crate::TaskPhoenixKey {
+            __key: {
+                $crate::__tokio::task_local! {
+                    static __KEY: $crate::SyncPhoenix<$t>;
+                }
+
+                fn __key() -> &'static $crate::__tokio::task::LocalKey<$crate::SyncPhoenix<$t>> {
+                    &__KEY
+                }
+
+                __key
+            },
+        };
+        $($crate::task_phoenix!($($rest)*);)?
+    );
+}
+
+/// Like `phoenix_tls!`, but declares `PhoenixMutKey`s, whose values can be mutated through
+/// `with_mut`.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! phoenix_tls_mut {
+    // empty (base case for the recursion)
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr $(; $($rest:tt)*)?) => (
+        $(#[$attr])* $vis const $name: $crate::PhoenixMutKey<$t> = $crate::PhoenixMutKey {
+            __key: {
+                $crate::phoenix_tls! {
+                    @key $name; __KEY, $crate::PhoenixMut<$t>, $crate::PhoenixMut::new($init)
+                }
+
+                __KEY
+            },
+        };
+        $($crate::phoenix_tls_mut!($($rest)*);)?
+    );
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty $(; $($rest:tt)*)?) => (
+        $(#[$attr])* $vis const $name: $crate::PhoenixMutKey<$t> = $crate::PhoenixMutKey {
+            __key: {
+                $crate::phoenix_tls! {
+                    @key $name; __KEY, $crate::PhoenixMut<$t>,
+                    $crate::PhoenixMut::new(::core::default::Default::default())
+                }
+
+                __KEY
+            },
+        };
+        $($crate::phoenix_tls_mut!($($rest)*);)?
+    );
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::{
+        alloc::System,
+        panic::{self, AssertUnwindSafe},
+        sync::atomic::{AtomicUsize, Ordering::SeqCst},
+        thread,
+    };
+
+    // Tests run in parallel, so every test declares its own `Case` with its own counters.
+    struct Counters {
+        allocations:  AtomicUsize,
+        unsubscribes: AtomicUsize,
+        drops:        AtomicUsize,
+    }
+
+    impl Counters {
+        const fn new() -> Self {
+            Counters {
+                allocations:  AtomicUsize::new(0),
+                unsubscribes: AtomicUsize::new(0),
+                drops:        AtomicUsize::new(0),
+            }
+        }
+
+        // (live allocations, unsubscribes, drops)
+        fn get(&self) -> (usize, usize, usize) {
+            (
+                self.allocations.load(SeqCst),
+                self.unsubscribes.load(SeqCst),
+                self.drops.load(SeqCst),
+            )
+        }
+    }
+
+    unsafe impl GlobalAlloc for Counters {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.allocations.fetch_add(1, SeqCst);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.allocations.fetch_sub(1, SeqCst);
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    trait Case: 'static {
+        const PANIC_IN_SUBSCRIBE: bool = false;
+        const PANIC_IN_UNSUBSCRIBE: bool = false;
+        const PANIC_IN_ON_DROP: bool = false;
+        const PANIC_IN_DROP: bool = false;
+
+        fn counters() -> &'static Counters;
+    }
+
+    macro_rules! case {
+        ($name:ident $(, $panic:ident)*) => {
+            struct $name;
+
+            impl Case for $name {
+                $(const $panic: bool = true;)*
+
+                fn counters() -> &'static Counters {
+                    static COUNTERS: Counters = Counters::new();
+                    &COUNTERS
+                }
+            }
+        };
+    }
+
+    // Allocated through its case's counters, and counts its unsubscribes and drops there.
+    struct Probe<C: Case>(PhantomData<C>);
+
+    impl<C: Case> Default for Probe<C> {
+        fn default() -> Self {
+            Probe(PhantomData)
+        }
+    }
+
+    impl<C: Case> PhoenixTarget for Probe<C> {
+        fn subscribe(&mut self) {
+            if C::PANIC_IN_SUBSCRIBE {
+                panic!("subscribe")
+            }
+        }
+
+        fn unsubscribe(&mut self) {
+            C::counters().unsubscribes.fetch_add(1, SeqCst);
+            if C::PANIC_IN_UNSUBSCRIBE {
+                panic!("unsubscribe")
+            }
+        }
+
+        fn on_drop(self: Pin<&mut Self>) {
+            if C::PANIC_IN_ON_DROP {
+                panic!("on_drop")
+            }
+        }
+
+        fn allocator() -> Option<PhoenixAllocator> {
+            // always the same static
+            Some(unsafe { PhoenixAllocator::new(C::counters()) })
+        }
+    }
+
+    impl<C: Case> Drop for Probe<C> {
+        fn drop(&mut self) {
+            C::counters().drops.fetch_add(1, SeqCst);
+            if C::PANIC_IN_DROP {
+                panic!("drop")
+            }
+        }
+    }
+
+    #[test]
+    fn last_drop_unsubscribes_once() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        let clones = [phoenix.clone(), phoenix.clone()];
+        assert_eq!(phoenix.strong_count(), 3);
+        drop(phoenix);
+        drop(clones);
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn strong_count_follows_clones_and_drops() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        assert_eq!(phoenix.strong_count(), 1);
+        let clone = phoenix.clone();
+        assert_eq!(phoenix.strong_count(), 2);
+        assert_eq!(clone.strong_count(), 2);
+        drop(clone);
+        assert_eq!(phoenix.strong_count(), 1);
+    }
+
+    #[test]
+    fn raw_round_trip_keeps_the_count() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        let raw = phoenix.clone().into_raw();
+        assert_eq!(raw, Phoenix::value_ptr(&phoenix));
+        assert_eq!(phoenix.strong_count(), 2);
+        drop(unsafe { Phoenix::from_raw(raw) });
+        assert_eq!(phoenix.strong_count(), 1);
+        drop(phoenix);
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn try_unwrap_unsubscribes_and_returns_the_value() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        let clone = phoenix.clone();
+        let phoenix = match Phoenix::try_unwrap(phoenix) {
+            Ok(_) => panic!("unwrapped a shared `Phoenix`"),
+            Err(phoenix) => phoenix,
+        };
+        drop(clone);
+
+        let weak = phoenix.downgrade();
+        let value = Phoenix::try_unwrap(phoenix).ok().unwrap();
+        assert!(weak.upgrade().is_none());
+        // the weak handle still holds the allocation
+        assert_eq!(C::counters().get(), (1, 1, 0));
+        drop(weak);
+        drop(value);
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn handles_share_one_count_update() {
+        case!(C);
+        crate::phoenix_tls! {
+            static PROBE: Probe<C>;
+        }
+
+        thread::spawn(|| {
+            let handles = PROBE.handles(3);
+            assert_eq!(handles[0].strong_count(), 4);
+            drop(handles);
+            assert_eq!(PROBE.handle().strong_count(), 2);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn clone_checked_reports_overflow() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        Phoenix::set_strong_count(&phoenix, usize::MAX);
+        assert_eq!(phoenix.clone_checked().err(), Some(CloneOverflow));
+        assert_eq!(phoenix.strong_count(), usize::MAX);
+
+        Phoenix::set_strong_count(&phoenix, usize::MAX - 1);
+        let clone = phoenix.clone_checked().unwrap();
+        assert_eq!(phoenix.strong_count(), usize::MAX);
+        mem::forget(clone);
+
+        Phoenix::set_strong_count(&phoenix, 1);
+        drop(phoenix);
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn clone_from_same_allocation_keeps_the_count() {
+        case!(C);
+        let mut phoenix = Phoenix::<Probe<C>>::new();
+        let clone = phoenix.clone();
+        phoenix.clone_from(&clone);
+        assert_eq!(phoenix.strong_count(), 2);
+
+        let mut other = Phoenix::<Probe<C>>::new();
+        other.clone_from(&phoenix);
+        assert!(Phoenix::ptr_eq(&other, &phoenix));
+        assert_eq!(phoenix.strong_count(), 3);
+        // the value `other` held before is gone
+        assert_eq!(C::counters().get(), (1, 1, 1));
+
+        drop((phoenix, clone, other));
+        assert_eq!(C::counters().get(), (0, 2, 2));
+    }
+
+    #[test]
+    fn from_value_many_unsubscribes_after_the_last_drop() {
+        case!(C);
+        let mut handles =
+            Phoenix::from_value_many(Probe::<C>::default(), NonZeroUsize::new(3).unwrap());
+        assert_eq!(handles.len(), 3);
+        assert_eq!(handles[0].strong_count(), 3);
+
+        while handles.len() > 1 {
+            handles.pop();
+            assert_eq!(C::counters().get(), (1, 0, 0));
+        }
+        handles.pop();
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn weak_fails_to_upgrade_after_the_last_drop() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        let weak = phoenix.downgrade();
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(phoenix.strong_count(), 2);
+        drop((phoenix, upgraded));
+
+        assert!(weak.upgrade().is_none());
+        assert_eq!(C::counters().get(), (1, 1, 1));
+    }
+
+    #[test]
+    fn weak_outlives_strong() {
+        case!(C);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        let weak = phoenix.downgrade();
+        let weak_clone = weak.clone();
+        assert_eq!(phoenix.weak_count(), 2);
+        drop(phoenix);
+
+        // the value is gone, but the allocation lives until the last weak handle
+        assert_eq!(C::counters().get(), (1, 1, 1));
+        drop(weak);
+        assert!(weak_clone.upgrade().is_none());
+        drop(weak_clone);
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn subscribe_panic_frees_the_allocation() {
+        case!(C, PANIC_IN_SUBSCRIBE);
+        let result = panic::catch_unwind(Phoenix::<Probe<C>>::new);
+        assert!(result.is_err());
+        assert_eq!(C::counters().get(), (0, 0, 1));
+
+        let result = panic::catch_unwind(SyncPhoenix::<Probe<C>>::new);
+        assert!(result.is_err());
+        assert_eq!(C::counters().get(), (0, 0, 2));
+    }
+
+    #[test]
+    fn unsubscribe_panic_still_drops_and_frees() {
+        case!(C, PANIC_IN_UNSUBSCRIBE);
+        let phoenix = Phoenix::<Probe<C>>::new();
+        let clone = phoenix.clone();
+        drop(phoenix);
+        assert_eq!(C::counters().get(), (1, 0, 0));
+        drop(clone);
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn on_drop_and_drop_panics_still_free() {
+        case!(C, PANIC_IN_UNSUBSCRIBE, PANIC_IN_ON_DROP, PANIC_IN_DROP);
+        drop(Phoenix::<Probe<C>>::new());
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn sync_phoenix_unsubscribe_panic_still_drops() {
+        case!(C, PANIC_IN_UNSUBSCRIBE, PANIC_IN_ON_DROP);
+        let phoenix = SyncPhoenix::<Probe<C>>::new();
+        thread::spawn(move || drop(phoenix)).join().unwrap();
+        assert_eq!(C::counters().get(), (0, 1, 1));
+    }
+
+    #[test]
+    fn unsubscribe_panic_at_thread_exit() {
+        case!(C, PANIC_IN_UNSUBSCRIBE);
+        crate::phoenix_tls! {
+            static PROBE: Probe<C>;
+        }
+
+        let thread = thread::spawn(|| PROBE.with(|_| ()));
+        assert!(thread.join().is_ok());
+        assert_eq!(C::counters().get(), (0, 1, 1));
+
+        // and a panicking temporary doesn't unwind out of the access either
+        let result = panic::catch_unwind(AssertUnwindSafe(|| PROBE.with_fresh(|_| ())));
+        assert!(result.is_ok());
+        assert_eq!(C::counters().get(), (0, 2, 2));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn for_each_live_reentrancy_panics_instead_of_aborting() {
+        #[derive(Default)]
+        struct Live;
+
+        static LIVE: ThreadRegistry<Live> = ThreadRegistry::new();
+
+        impl PhoenixTarget for Live {
+            fn subscribe(&mut self) {}
+
+            fn unsubscribe(&mut self) {}
+
+            fn live_registry() -> Option<&'static ThreadRegistry<Self>> {
+                Some(&LIVE)
+            }
+        }
+
+        let mut phoenix = Some(Phoenix::<Live>::new());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            Phoenix::<Live>::for_each_live(|_| drop(phoenix.take()))
+        }));
+        assert!(result.is_err());
+
+        // the leaked value is still registered, so there is something to iterate over
+        let result = panic::catch_unwind(|| {
+            Phoenix::<Live>::for_each_live(|_| drop(Phoenix::<Live>::new()))
+        });
+        assert!(result.is_err());
+        assert_eq!(LIVE.len(), 1);
+    }
+
+    #[test]
+    fn no_subscribe_derefs_mutably() {
+        let mut names = NoSubscribe(vec!["main"]);
+        names.push("worker");
+        names[0] = "first";
+        assert_eq!(names.0, ["first", "worker"]);
+    }
+
+    #[test]
+    fn hash_set_dedups_by_value() {
+        #[derive(PartialEq, Eq, Hash)]
+        struct Id(u32);
+
+        impl PhoenixTarget for Id {
+            fn subscribe(&mut self) {}
+
+            fn unsubscribe(&mut self) {}
+        }
+
+        let mut set = std::collections::HashSet::new();
+        assert!(set.insert(Phoenix::from_value(Id(1))));
+        // a separate allocation, but an equal value
+        assert!(!set.insert(Phoenix::from_value(Id(1))));
+        assert!(set.insert(Phoenix::from_value(Id(2))));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&Phoenix::from_value(Id(2))));
+    }
+
+    #[test]
+    fn get_mut_requires_a_unique_handle() {
+        let mut phoenix = Phoenix::from_value(NoSubscribe(1));
+        let clone = phoenix.clone();
+        assert!(unsafe { Phoenix::get_mut(&mut phoenix) }.is_none());
+        drop(clone);
+
+        let weak = phoenix.downgrade();
+        assert!(unsafe { Phoenix::get_mut(&mut phoenix) }.is_none());
+        drop(weak);
+
+        // nothing else shares the value's address
+        unsafe { Phoenix::get_mut(&mut phoenix) }.unwrap().0 = 2;
+        assert_eq!(phoenix.0, 2);
+    }
+
+    #[test]
+    fn make_mut_clones_only_when_shared() {
+        static SUBSCRIBES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone)]
+        struct Tally(u32);
+
+        impl PhoenixTarget for Tally {
+            fn subscribe(&mut self) {
+                SUBSCRIBES.fetch_add(1, SeqCst);
+            }
+
+            fn unsubscribe(&mut self) {}
+        }
+
+        let mut phoenix = Phoenix::from_value(Tally(1));
+        let shared = phoenix.clone();
+        // nothing shares the values' addresses
+        unsafe { Phoenix::make_mut(&mut phoenix) }.0 = 2;
+        assert!(!Phoenix::ptr_eq(&phoenix, &shared));
+        assert_eq!((phoenix.0, shared.0), (2, 1));
+        assert_eq!(phoenix.strong_count(), 1);
+        assert_eq!(shared.strong_count(), 1);
+        assert_eq!(SUBSCRIBES.load(SeqCst), 2);
+
+        let address = Phoenix::value_ptr(&phoenix);
+        unsafe { Phoenix::make_mut(&mut phoenix) }.0 = 3;
+        assert_eq!(Phoenix::value_ptr(&phoenix), address);
+        assert_eq!(phoenix.0, 3);
+        assert_eq!(SUBSCRIBES.load(SeqCst), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_subscribes_a_new_allocation() {
+        static SUBSCRIBES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Config {
+            name:  String,
+            level: u32,
+        }
+
+        impl PhoenixTarget for Config {
+            fn subscribe(&mut self) {
+                SUBSCRIBES.fetch_add(1, SeqCst);
+            }
+
+            fn unsubscribe(&mut self) {}
+        }
+
+        let phoenix = Phoenix::from_value(Config {
+            name:  "main".into(),
+            level: 3,
+        });
+        let json = serde_json::to_string(&phoenix).unwrap();
+        assert_eq!(json, r#"{"name":"main","level":3}"#);
+
+        let copy: Phoenix<Config> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy, phoenix);
+        assert!(!Phoenix::ptr_eq(&copy, &phoenix));
+        assert_eq!(SUBSCRIBES.load(SeqCst), 2);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metrics_count_live_values_by_type() {
+        case!(C);
+        crate::phoenix_tls! {
+            static PROBE: Probe<C>;
+        }
+
+        thread::spawn(|| {
+            let handle = PROBE.handle();
+            drop(SyncPhoenix::<Probe<C>>::new());
+            let metrics = Phoenix::<Probe<C>>::metrics();
+            assert_eq!((metrics.created, metrics.live), (2, 1));
+            drop(handle);
+        })
+        .join()
+        .unwrap();
+        let metrics = Phoenix::<Probe<C>>::metrics();
+        assert_eq!((metrics.created, metrics.live), (2, 0));
+    }
+
+    #[test]
+    fn sync_phoenix_debug_shows_the_value_and_count() {
+        let phoenix = SyncPhoenix::from_value(PhoenixCell::new(7));
+        let _clone = phoenix.clone();
+        assert_eq!(
+            format!("{:?}", phoenix),
+            format!(
+                "SyncPhoenix {{ value: {:?}, strong_count: 2 }}",
+                PhoenixCell::new(7)
+            )
+        );
+    }
+
+    #[test]
+    fn to_arc_outlives_the_creating_thread() {
+        #[derive(Clone)]
+        struct Name(&'static str);
+
+        impl PhoenixTarget for Name {
+            fn subscribe(&mut self) {}
+
+            fn unsubscribe(&mut self) {}
+        }
+
+        crate::phoenix_tls! {
+            static NAME: Name = Name("worker");
+        }
+
+        let arc = thread::spawn(|| NAME.handle().to_arc()).join().unwrap();
+        assert_eq!(arc.0, "worker");
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    // meant for miri, which flags the last handle borrowing the value mutably while another thread
+    // can still read it through the registry
+    #[test]
+    fn for_each_live_races_the_last_drop() {
+        struct Live(u32);
+
+        static LIVE: ThreadRegistry<Live> = ThreadRegistry::new();
+
+        impl PhoenixTarget for Live {
+            fn subscribe(&mut self) {}
+
+            fn unsubscribe(&mut self) {
+                self.0 = 0
+            }
+
+            fn live_registry() -> Option<&'static ThreadRegistry<Self>> {
+                Some(&LIVE)
+            }
+        }
+
+        let done = Arc::new(core::sync::atomic::AtomicBool::new(false));
+        let reader = thread::spawn({
+            let done = done.clone();
+            move || {
+                while !done.load(SeqCst) {
+                    Phoenix::<Live>::for_each_live(|live| assert_eq!(live.0, 1));
+                }
+            }
+        });
+        for _ in 0..40 {
+            drop(Phoenix::from_value(Live(1)));
+            drop(SyncPhoenix::from_value(Live(1)));
+            assert!(Phoenix::try_unwrap(Phoenix::from_value(Live(1))).is_ok());
+        }
+        done.store(true, SeqCst);
+        reader.join().unwrap();
+        assert!(LIVE.is_empty());
+    }
 }
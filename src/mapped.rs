@@ -0,0 +1,48 @@
+use crate::{Phoenix, PhoenixTarget};
+use core::{fmt, ops::Deref, ptr::NonNull};
+
+/// A handle to part of a `Phoenix<T>`'s value, created with `Phoenix::map`.
+///
+/// Keeps the whole allocation alive, like the `Phoenix` it was created from.
+pub struct MappedPhoenix<T: 'static + PhoenixTarget, U: ?Sized> {
+    // never read, only keeps `value` alive
+    _phoenix: Phoenix<T>,
+    value:    NonNull<U>,
+}
+
+impl<T: 'static + PhoenixTarget, U: ?Sized> MappedPhoenix<T, U> {
+    #[inline]
+    pub(crate) fn new<F: FnOnce(&T) -> &U>(phoenix: Phoenix<T>, f: F) -> Self {
+        let value = NonNull::from(f(&phoenix));
+        MappedPhoenix {
+            _phoenix: phoenix,
+            value,
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget, U: ?Sized> Clone for MappedPhoenix<T, U> {
+    #[inline]
+    fn clone(&self) -> Self {
+        MappedPhoenix {
+            _phoenix: self._phoenix.clone(),
+            value:    self.value,
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget, U: ?Sized> Deref for MappedPhoenix<T, U> {
+    type Target = U;
+
+    #[inline]
+    fn deref(&self) -> &U {
+        // `value` borrows from the allocation, which `_phoenix` keeps alive
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T: 'static + PhoenixTarget, U: ?Sized + fmt::Debug> fmt::Debug for MappedPhoenix<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,137 @@
+#[cfg(feature = "std")]
+use core::any::TypeId;
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+cfg_if::cfg_if! {
+    if #[cfg(target_has_atomic = "64")] {
+        static CREATED: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+
+        #[inline]
+        fn count_create() {
+            CREATED.fetch_add(1, Relaxed);
+            LIVE.fetch_add(1, Relaxed);
+        }
+
+        #[inline]
+        fn count_destroy() {
+            LIVE.fetch_sub(1, Relaxed);
+        }
+
+        #[inline]
+        fn created() -> u64 {
+            CREATED.load(Relaxed)
+        }
+    } else if #[cfg(target_has_atomic = "ptr")] {
+        // wraps after `usize::MAX` values, rather than never
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+        #[inline]
+        fn count_create() {
+            CREATED.fetch_add(1, Relaxed);
+            LIVE.fetch_add(1, Relaxed);
+        }
+
+        #[inline]
+        fn count_destroy() {
+            LIVE.fetch_sub(1, Relaxed);
+        }
+
+        #[inline]
+        fn created() -> u64 {
+            CREATED.load(Relaxed) as u64
+        }
+    } else {
+        // no read-modify-write atomics, so updates made at the same time, from an interrupt handler
+        // or another core, may be lost
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+        #[inline]
+        fn count_create() {
+            CREATED.store(CREATED.load(Relaxed).wrapping_add(1), Relaxed);
+            LIVE.store(LIVE.load(Relaxed).wrapping_add(1), Relaxed);
+        }
+
+        #[inline]
+        fn count_destroy() {
+            LIVE.store(LIVE.load(Relaxed).wrapping_sub(1), Relaxed);
+        }
+
+        #[inline]
+        fn created() -> u64 {
+            CREATED.load(Relaxed) as u64
+        }
+    }
+}
+
+// the same counters, by type
+#[cfg(feature = "std")]
+static BY_TYPE: Mutex<Option<HashMap<TypeId, PhoenixMetrics>>> = Mutex::new(None);
+
+/// A snapshot of how many `Phoenix` and `SyncPhoenix` values exist, across all threads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhoenixMetrics {
+    /// The number of values ever created, including temporaries.
+    pub created: u64,
+    /// The number of values that have been created and not yet unsubscribed.
+    pub live:    usize,
+}
+
+/// Returns the current `PhoenixMetrics`, summed over every `PhoenixTarget` type.
+///
+/// `Phoenix::metrics` returns them for a single type. The counters are updated with relaxed
+/// atomics, so a snapshot taken while other threads create or drop values may be slightly out of
+/// date.
+#[inline]
+pub fn metrics() -> PhoenixMetrics {
+    PhoenixMetrics {
+        created: created(),
+        live:    LIVE.load(Relaxed),
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn metrics_of<T: 'static>() -> PhoenixMetrics {
+    let by_type = BY_TYPE.lock().unwrap_or_else(PoisonError::into_inner);
+    by_type
+        .as_ref()
+        .and_then(|by_type| by_type.get(&TypeId::of::<T>()))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[inline]
+#[cfg_attr(not(feature = "std"), allow(clippy::extra_unused_type_parameters))]
+pub(crate) fn on_create<T: 'static>() {
+    count_create();
+    #[cfg(feature = "std")]
+    update::<T>(|metrics| {
+        metrics.created += 1;
+        metrics.live += 1;
+    });
+}
+
+// Always follows the `on_create` of the same value, so the count can't underflow.
+#[inline]
+#[cfg_attr(not(feature = "std"), allow(clippy::extra_unused_type_parameters))]
+pub(crate) fn on_destroy<T: 'static>() {
+    count_destroy();
+    #[cfg(feature = "std")]
+    update::<T>(|metrics| metrics.live -= 1);
+}
+
+#[cfg(feature = "std")]
+#[inline(never)]
+fn update<T: 'static>(f: impl FnOnce(&mut PhoenixMetrics)) {
+    let mut by_type = BY_TYPE.lock().unwrap_or_else(PoisonError::into_inner);
+    f(by_type
+        .get_or_insert_with(HashMap::new)
+        .entry(TypeId::of::<T>())
+        .or_default())
+}
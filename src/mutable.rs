@@ -0,0 +1,165 @@
+use crate::{Phoenix, PhoenixKey, PhoenixTarget};
+use core::cell::{Ref, RefCell, RefMut};
+
+/// A `PhoenixTarget` with no hooks, holding a value in a `RefCell` for mutable access through
+/// shared handles. Declared with `phoenix_tls_mut!`.
+///
+/// A mutable borrow can replace the wrapped value outright, so it can't be subscribed or
+/// registered anywhere by address. Targets that need hooks keep their mutable state in `Cell` or
+/// `RefCell` fields instead.
+#[derive(Default)]
+pub struct PhoenixMut<T>(RefCell<T>);
+
+impl<T> PhoenixMut<T> {
+    /// Wraps `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        PhoenixMut(RefCell::new(value))
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is mutably borrowed.
+    #[inline]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        match self.0.try_borrow() {
+            Ok(value) => value,
+            Err(_) => panic!(
+                "phoenix thread local of type `{}` borrowed while it is mutably borrowed",
+                core::any::type_name::<T>()
+            ),
+        }
+    }
+
+    /// Mutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is borrowed.
+    #[inline]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        match self.0.try_borrow_mut() {
+            Ok(value) => value,
+            Err(_) => panic!(
+                "phoenix thread local of type `{}` mutably borrowed while it is already borrowed",
+                core::any::type_name::<T>()
+            ),
+        }
+    }
+
+    /// Returns the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T> PhoenixTarget for PhoenixMut<T> {
+    #[inline]
+    fn subscribe(&mut self) {}
+
+    #[inline]
+    fn unsubscribe(&mut self) {}
+}
+
+/// A phoenix thread local with mutable access, declared with `phoenix_tls_mut!`.
+///
+/// `with_mut` works while other handles exist, and only panics if the value is already borrowed.
+pub struct PhoenixMutKey<T: 'static> {
+    #[doc(hidden)]
+    pub __key: PhoenixKey<PhoenixMut<T>>,
+}
+
+impl<T: 'static> Clone for PhoenixMutKey<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: 'static> Copy for PhoenixMutKey<T> {}
+
+impl<T: 'static> PhoenixMutKey<T> {
+    /// Calls `f` with a shared borrow of this thread's value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside `with_mut` on the same key.
+    #[inline]
+    pub fn with<F: FnOnce(&T) -> O, O>(self, f: F) -> O {
+        self.__key.with(|value| f(&value.borrow()))
+    }
+
+    /// Calls `f` with a mutable borrow of this thread's value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside `with` or `with_mut` on the same key.
+    #[inline]
+    pub fn with_mut<F: FnOnce(&mut T) -> O, O>(self, f: F) -> O {
+        self.__key.with(|value| f(&mut value.borrow_mut()))
+    }
+
+    /// Returns a handle to this thread's value.
+    #[inline]
+    pub fn handle(self) -> Phoenix<PhoenixMut<T>> {
+        self.__key.handle()
+    }
+
+    /// Returns the underlying `PhoenixKey`, for the rest of its API.
+    #[inline]
+    pub fn key(self) -> PhoenixKey<PhoenixMut<T>> {
+        self.__key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::phoenix_tls_mut;
+    use std::panic;
+
+    phoenix_tls_mut! {
+        static NAMES: Vec<&'static str>;
+        static LIMIT: u32 = 8;
+    }
+
+    #[test]
+    fn with_mut_is_visible_to_with() {
+        assert_eq!(LIMIT.with(|limit| *limit), 8);
+        NAMES.with_mut(|names| names.push("main"));
+        NAMES.with_mut(|names| names.push("worker"));
+        assert_eq!(NAMES.with(|names| names.clone()), ["main", "worker"]);
+    }
+
+    #[test]
+    fn conflicting_borrow_panics_with_the_type() {
+        let message = panic::catch_unwind(|| LIMIT.with(|_| LIMIT.with_mut(|_| ())))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert_eq!(
+            *message,
+            "phoenix thread local of type `u32` mutably borrowed while it is already borrowed"
+        );
+    }
+
+    #[cfg(feature = "inventory")]
+    #[test]
+    fn declared_keys_use_the_static_names() {
+        let type_name = |name| {
+            crate::declared_keys()
+                .find(|key| key.name() == name)
+                .map(|key| key.type_name())
+        };
+        assert_eq!(
+            type_name("NAMES"),
+            Some(core::any::type_name::<crate::PhoenixMut<Vec<&str>>>())
+        );
+        assert_eq!(
+            type_name("LIMIT"),
+            Some(core::any::type_name::<crate::PhoenixMut<u32>>())
+        );
+    }
+}
@@ -0,0 +1,745 @@
+use core::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+    ptr::{self, NonNull},
+    sync::atomic::Ordering::Relaxed,
+};
+use std::{
+    collections::HashMap,
+    sync::PoisonError,
+    thread::{self, ThreadId},
+};
+
+cfg_if::cfg_if! {
+    if #[cfg(loom)] {
+        use loom::sync::{atomic::AtomicPtr, Mutex, MutexGuard};
+    } else {
+        use core::sync::atomic::AtomicPtr;
+        use std::sync::{Mutex, MutexGuard};
+    }
+}
+
+// Defines a `const fn`, except under loom, whose primitives can't be created in constants.
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])* $vis const fn $($rest)*
+
+        #[cfg(loom)]
+        $(#[$attr])* $vis fn $($rest)*
+    };
+}
+
+// loom runs every modeled thread on the same OS thread, so they would all share `ITERATING`.
+const TRACK_ITERATION: bool = cfg!(all(debug_assertions, not(loom)));
+
+thread_local! {
+    // The registry whose `for_each` is running on this thread, if any. Only maintained with debug
+    // assertions on, to catch callbacks that would deadlock on the registry's lock.
+    static ITERATING: Cell<*const ()> = const { Cell::new(ptr::null()) };
+}
+
+// Marks `registry` as being iterated on this thread until dropped.
+struct IterationGuard {
+    previous: *const (),
+}
+
+impl IterationGuard {
+    #[inline]
+    fn new(registry: *const ()) -> Self {
+        let previous = if TRACK_ITERATION {
+            ITERATING
+                .try_with(|x| x.replace(registry))
+                .unwrap_or(ptr::null())
+        } else {
+            ptr::null()
+        };
+        IterationGuard { previous }
+    }
+}
+
+impl Drop for IterationGuard {
+    #[inline]
+    fn drop(&mut self) {
+        if TRACK_ITERATION {
+            let _ = ITERATING.try_with(|x| x.set(self.previous));
+        }
+    }
+}
+
+#[inline]
+fn debug_assert_not_iterating(registry: *const ()) {
+    if TRACK_ITERATION {
+        let iterating = ITERATING.try_with(Cell::get).unwrap_or(ptr::null());
+        assert!(
+            iterating != registry,
+            "registry accessed from inside its own `for_each`, which would deadlock"
+        );
+    }
+}
+
+/// A list of the live instances of a `PhoenixTarget`.
+///
+/// Phoenix values live at a stable address between `subscribe` and `unsubscribe`, so the registry
+/// can hand out references to every live instance in between. Return it from
+/// `PhoenixTarget::live_registry` to have each value registered after `subscribe` and deregistered
+/// before `unsubscribe`.
+pub struct ThreadRegistry<T> {
+    list: Mutex<Vec<NonNull<T>>>,
+}
+
+// The registry only ever hands out `&T`s, potentially to other threads.
+unsafe impl<T: Sync> Send for ThreadRegistry<T> {}
+unsafe impl<T: Sync> Sync for ThreadRegistry<T> {}
+
+impl<T> Default for ThreadRegistry<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ThreadRegistry<T> {
+    const_fn! {
+        /// Creates an empty registry.
+        #[inline]
+        pub fn new() -> Self {
+            ThreadRegistry {
+                list: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    /// Adds `value` to the registry.
+    ///
+    /// # Safety
+    ///
+    /// `value` must stay alive at its current address until it is passed to `deregister`, and must
+    /// not be borrowed mutably in the meantime, as `for_each` may be reading it on another thread.
+    /// That rules out deregistering a phoenix value from `unsubscribe`, which takes `&mut self`.
+    /// Return the registry from `PhoenixTarget::live_registry` instead, which deregisters each
+    /// value before unsubscribing it.
+    #[inline]
+    pub unsafe fn register(&self, value: &T) {
+        self.lock().push(NonNull::from(value))
+    }
+
+    /// Removes `value` from the registry, returning `false` if it was never registered.
+    #[inline]
+    pub fn deregister(&self, value: &T) -> bool {
+        let ptr = NonNull::from(value);
+        let mut list = self.lock();
+        match list.iter().position(|x| *x == ptr) {
+            Some(position) => {
+                list.swap_remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `f` on every registered value.
+    ///
+    /// The registry is locked for the duration, so no value can be deregistered (and therefore
+    /// destroyed) while `f` is running. `f` must not register or deregister values itself, as that
+    /// would deadlock; with debug assertions on, doing so panics instead.
+    #[inline]
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        let list = self.lock();
+        let _guard = IterationGuard::new(self as *const _ as *const ());
+        for ptr in list.iter() {
+            // registered values are alive until they are deregistered, which requires the lock
+            f(unsafe { ptr.as_ref() })
+        }
+    }
+
+    /// Returns the number of registered values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if no values are registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    // Lets `Phoenix` report a reentrant `for_each_live` before it gets to its `extern "C"`
+    // `dealloc`, where the panic would abort.
+    #[inline]
+    pub(crate) fn debug_assert_not_iterating(&self) {
+        debug_assert_not_iterating(self as *const _ as *const ())
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Vec<NonNull<T>>> {
+        self.debug_assert_not_iterating();
+        // the list is never left in an inconsistent state, so poisoning can be ignored
+        self.list.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A `ThreadRegistry` split into `N` independently locked shards, for targets that are created
+/// and destroyed on many threads at once.
+///
+/// Values are assigned to a shard by hashing their address. `N` must be nonzero.
+pub struct ShardedRegistry<T, const N: usize> {
+    shards: [ThreadRegistry<T>; N],
+}
+
+impl<T, const N: usize> Default for ShardedRegistry<T, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> ShardedRegistry<T, N> {
+    /// Creates an empty registry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    #[cfg(not(loom))]
+    #[inline]
+    pub const fn new() -> Self {
+        assert!(N > 0, "a `ShardedRegistry` needs at least one shard");
+        ShardedRegistry {
+            shards: [const { ThreadRegistry::new() }; N],
+        }
+    }
+
+    #[cfg(loom)]
+    #[inline]
+    pub fn new() -> Self {
+        assert!(N > 0, "a `ShardedRegistry` needs at least one shard");
+        ShardedRegistry {
+            shards: core::array::from_fn(|_| ThreadRegistry::new()),
+        }
+    }
+
+    /// Adds `value` to its shard.
+    ///
+    /// # Safety
+    ///
+    /// See `ThreadRegistry::register`.
+    #[inline]
+    pub unsafe fn register(&self, value: &T) {
+        self.shard(value).register(value)
+    }
+
+    /// Removes `value` from its shard, returning `false` if it was never registered.
+    #[inline]
+    pub fn deregister(&self, value: &T) -> bool {
+        self.shard(value).deregister(value)
+    }
+
+    /// Calls `f` on every registered value.
+    ///
+    /// Only one shard is locked at a time, so values in other shards may come and go while `f`
+    /// runs; `f` is only ever passed live values. `f` must not register or deregister values
+    /// itself.
+    #[inline]
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        for shard in &self.shards {
+            shard.for_each(&mut f)
+        }
+    }
+
+    /// Returns the number of registered values, summed over all the shards.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(ThreadRegistry::len).sum()
+    }
+
+    /// Returns `true` if no values are registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(ThreadRegistry::is_empty)
+    }
+
+    #[inline]
+    fn shard(&self, value: &T) -> &ThreadRegistry<T> {
+        // fibonacci hashing, so that aligned addresses still spread across every shard
+        let hash = (value as *const T as usize as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        &self.shards[((hash >> 32) as usize) % N]
+    }
+}
+
+/// A `ThreadRegistry` with room for at most `N` values, stored inline instead of on the heap.
+pub struct InlineRegistry<T, const N: usize> {
+    slots: Mutex<[Option<NonNull<T>>; N]>,
+}
+
+// The registry only ever hands out `&T`s, potentially to other threads.
+unsafe impl<T: Sync, const N: usize> Send for InlineRegistry<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for InlineRegistry<T, N> {}
+
+impl<T, const N: usize> Default for InlineRegistry<T, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> InlineRegistry<T, N> {
+    const_fn! {
+        /// Creates an empty registry.
+        #[inline]
+        pub fn new() -> Self {
+            InlineRegistry {
+                slots: Mutex::new([None; N]),
+            }
+        }
+    }
+
+    /// Adds `value` to the first free slot, returning `RegistryFull` if there is none.
+    ///
+    /// # Safety
+    ///
+    /// See `ThreadRegistry::register`. Values that fail to register don't need to be deregistered.
+    #[inline]
+    pub unsafe fn register(&self, value: &T) -> Result<(), RegistryFull> {
+        let mut slots = self.lock();
+        match slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(NonNull::from(value));
+                Ok(())
+            }
+            None => Err(RegistryFull),
+        }
+    }
+
+    /// Removes `value` from the registry, returning `false` if it was never registered.
+    #[inline]
+    pub fn deregister(&self, value: &T) -> bool {
+        let ptr = NonNull::from(value);
+        let mut slots = self.lock();
+        match slots.iter_mut().find(|slot| **slot == Some(ptr)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `f` on every registered value.
+    ///
+    /// See `ThreadRegistry::for_each`.
+    #[inline]
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        let slots = self.lock();
+        let _guard = IterationGuard::new(self as *const _ as *const ());
+        for ptr in slots.iter().flatten() {
+            // registered values are alive until they are deregistered, which requires the lock
+            f(unsafe { ptr.as_ref() })
+        }
+    }
+
+    /// Returns the number of registered values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Returns `true` if no values are registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().iter().all(Option::is_none)
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, [Option<NonNull<T>>; N]> {
+        debug_assert_not_iterating(self as *const _ as *const ());
+        // the slots are never left in an inconsistent state, so poisoning can be ignored
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The error returned by `InlineRegistry::register` when every slot is taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegistryFull;
+
+impl fmt::Display for RegistryFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("registry is full")
+    }
+}
+
+impl std::error::Error for RegistryFull {}
+
+/// The links embedded in a `PhoenixTarget` that is tracked by an `IntrusiveRegistry`.
+///
+/// The links are only ever read or written while the owning registry is locked.
+pub struct IntrusiveNode<T> {
+    // The registry the node is linked into, or null. Atomic, and outside of `links`, so that any
+    // registry can check whether it owns the node without a data race. Only changed while the
+    // owning registry is locked.
+    registry: AtomicPtr<IntrusiveRegistry<T>>,
+    links:    UnsafeCell<Links<T>>,
+}
+
+struct Links<T> {
+    owner: Option<NonNull<T>>,
+    prev:  Option<NonNull<IntrusiveNode<T>>>,
+    next:  Option<NonNull<IntrusiveNode<T>>>,
+}
+
+// The links are protected by the registry's lock, which only ever hands out `&T`s.
+unsafe impl<T: Sync> Send for IntrusiveNode<T> {}
+unsafe impl<T: Sync> Sync for IntrusiveNode<T> {}
+
+impl<T> Default for IntrusiveNode<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> IntrusiveNode<T> {
+    const_fn! {
+        /// Creates a node that is not part of any registry.
+        #[inline]
+        pub fn new() -> Self {
+            IntrusiveNode {
+                registry: AtomicPtr::new(ptr::null_mut()),
+                links:    UnsafeCell::new(Links {
+                    owner: None,
+                    prev:  None,
+                    next:  None,
+                }),
+            }
+        }
+    }
+}
+
+/// A registry of live instances that links them together through an embedded `IntrusiveNode`.
+///
+/// Unlike `ThreadRegistry`, removal is O(1) and the registry never allocates. This relies on the
+/// address stability between `subscribe` and `unsubscribe`: both the target and its node stay at
+/// the same address while they are linked into the list.
+pub struct IntrusiveRegistry<T> {
+    head: Mutex<Option<NonNull<IntrusiveNode<T>>>>,
+}
+
+unsafe impl<T: Sync> Send for IntrusiveRegistry<T> {}
+unsafe impl<T: Sync> Sync for IntrusiveRegistry<T> {}
+
+impl<T> Default for IntrusiveRegistry<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> IntrusiveRegistry<T> {
+    const_fn! {
+        /// Creates an empty registry.
+        #[inline]
+        pub fn new() -> Self {
+            IntrusiveRegistry {
+                head: Mutex::new(None),
+            }
+        }
+    }
+
+    /// Links `value` into the registry through `node`.
+    ///
+    /// # Safety
+    ///
+    /// `value` and `node` must stay alive at their current addresses until `node` is passed to
+    /// `deregister`, and `value` must not be borrowed mutably in the meantime, as `for_each` may be
+    /// reading it on another thread. As with `ThreadRegistry::register`, a phoenix value can't be
+    /// deregistered from its own `unsubscribe`. `node` must not already be linked into a registry.
+    #[inline]
+    pub unsafe fn register(&self, value: &T, node: &IntrusiveNode<T>) {
+        let mut head = self.lock();
+        let node_ptr = NonNull::from(node);
+        debug_assert!(
+            node.registry.load(Relaxed).is_null(),
+            "`IntrusiveNode` registered twice"
+        );
+        node.registry.store(self as *const _ as *mut _, Relaxed);
+        let links = &mut *node.links.get();
+        links.owner = Some(NonNull::from(value));
+        links.prev = None;
+        links.next = *head;
+        if let Some(next) = *head {
+            (*next.as_ref().links.get()).prev = Some(node_ptr);
+        }
+        *head = Some(node_ptr);
+    }
+
+    /// Unlinks `node` from the registry, returning `false` if it was not linked into this registry.
+    #[inline]
+    pub fn deregister(&self, node: &IntrusiveNode<T>) -> bool {
+        let mut head = self.lock();
+        // only ever set to this registry while it is locked, as it is now. another registry's
+        // nodes are never touched beyond this check.
+        if !ptr::eq(node.registry.load(Relaxed), self) {
+            return false;
+        }
+        node.registry.store(ptr::null_mut(), Relaxed);
+        // the lock is held, and every node linked into this registry is alive
+        unsafe {
+            let links = &mut *node.links.get();
+            match links.prev {
+                Some(prev) => (*prev.as_ref().links.get()).next = links.next,
+                None => *head = links.next,
+            }
+            if let Some(next) = links.next {
+                (*next.as_ref().links.get()).prev = links.prev;
+            }
+            links.owner = None;
+            links.prev = None;
+            links.next = None;
+        }
+        true
+    }
+
+    /// Calls `f` on every registered value.
+    ///
+    /// The registry is locked for the duration, so no value can be deregistered (and therefore
+    /// destroyed) while `f` is running. `f` must not register or deregister values itself, as that
+    /// would deadlock; with debug assertions on, doing so panics instead.
+    #[inline]
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        let head = self.lock();
+        let _guard = IterationGuard::new(self as *const _ as *const ());
+        let mut cur = *head;
+        while let Some(node) = cur {
+            // the lock is held, and every node linked into this registry is alive
+            unsafe {
+                let links = &*node.as_ref().links.get();
+                if let Some(owner) = links.owner {
+                    f(owner.as_ref())
+                }
+                cur = links.next;
+            }
+        }
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Option<NonNull<IntrusiveNode<T>>>> {
+        debug_assert_not_iterating(self as *const _ as *const ());
+        self.head.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A registry that maps each thread to its live instance, for looking up a specific thread's value
+/// by `ThreadId`.
+///
+/// `register` uses the id of the calling thread, which is the owning thread when called from
+/// `subscribe`. Each thread has at most one entry, and registering again replaces it, so only the
+/// thread local's own value should be registered. Override `on_resurrect` to keep temporaries out.
+pub struct ThreadIdRegistry<T> {
+    map: Mutex<Option<HashMap<ThreadId, NonNull<T>>>>,
+}
+
+// The registry only ever hands out `&T`s, potentially to other threads.
+unsafe impl<T: Sync> Send for ThreadIdRegistry<T> {}
+unsafe impl<T: Sync> Sync for ThreadIdRegistry<T> {}
+
+impl<T> Default for ThreadIdRegistry<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ThreadIdRegistry<T> {
+    const_fn! {
+        /// Creates an empty registry.
+        #[inline]
+        pub fn new() -> Self {
+            ThreadIdRegistry {
+                map: Mutex::new(None),
+            }
+        }
+    }
+
+    /// Adds `value` to the registry as the current thread's instance.
+    ///
+    /// # Safety
+    ///
+    /// `value` must stay alive at its current address until it is passed to `deregister`, and must
+    /// not be borrowed mutably while another thread may be inside `with`. See
+    /// `ThreadRegistry::register`.
+    #[inline]
+    pub unsafe fn register(&self, value: &T) {
+        let id = thread::current().id();
+        self.lock()
+            .get_or_insert_with(HashMap::new)
+            .insert(id, NonNull::from(value));
+    }
+
+    /// Removes `value` from the registry, returning `false` if it isn't registered.
+    ///
+    /// This looks `value` up by address rather than by the current thread's id, which may no longer
+    /// be available when `unsubscribe` runs during thread teardown.
+    #[inline]
+    pub fn deregister(&self, value: &T) -> bool {
+        let ptr = NonNull::from(value);
+        let mut map = self.lock();
+        let map = match &mut *map {
+            Some(map) => map,
+            None => return false,
+        };
+        let len = map.len();
+        map.retain(|_, x| *x != ptr);
+        map.len() != len
+    }
+
+    /// Calls `f` on the instance registered by the thread `id`, if there is one.
+    ///
+    /// The registry is locked while `f` runs, so the instance can't be deregistered (and therefore
+    /// destroyed) in the meantime. `f` must not register or deregister values itself, as that would
+    /// deadlock; with debug assertions on, doing so panics instead.
+    #[inline]
+    pub fn with<R>(&self, id: ThreadId, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let map = self.lock();
+        let _guard = IterationGuard::new(self as *const _ as *const ());
+        let ptr = map.as_ref()?.get(&id)?;
+        // registered values are alive until they are deregistered, which requires the lock
+        Some(f(unsafe { ptr.as_ref() }))
+    }
+
+    /// Calls `f` on every registered thread id and instance.
+    ///
+    /// Same locking rules as `with`.
+    #[inline]
+    pub fn for_each(&self, mut f: impl FnMut(ThreadId, &T)) {
+        let map = self.lock();
+        let _guard = IterationGuard::new(self as *const _ as *const ());
+        for (&id, ptr) in map.iter().flatten() {
+            // registered values are alive until they are deregistered, which requires the lock
+            f(id, unsafe { ptr.as_ref() })
+        }
+    }
+
+    /// Returns the number of registered values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().as_ref().map_or(0, HashMap::len)
+    }
+
+    /// Returns `true` if no values are registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Option<HashMap<ThreadId, NonNull<T>>>> {
+        debug_assert_not_iterating(self as *const _ as *const ());
+        // the map is never left in an inconsistent state, so poisoning can be ignored
+        self.map.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{phoenix_tls, PhoenixTarget};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        mpsc,
+    };
+
+    // the same setup as examples/thread_registry.rs
+    static THREAD_LIST: ThreadRegistry<Thread> = ThreadRegistry::new();
+
+    static THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+    struct Thread {
+        id: usize,
+    }
+
+    impl Default for Thread {
+        fn default() -> Self {
+            let id = THREAD_ID.fetch_add(1, Relaxed);
+            Self { id }
+        }
+    }
+
+    impl PhoenixTarget for Thread {
+        fn subscribe(&mut self) {}
+
+        fn unsubscribe(&mut self) {}
+
+        fn live_registry() -> Option<&'static ThreadRegistry<Self>> {
+            Some(&THREAD_LIST)
+        }
+    }
+
+    phoenix_tls! {
+        static THREAD: Thread;
+    }
+
+    static BY_ID: ThreadIdRegistry<Named> = ThreadIdRegistry::new();
+
+    struct Named(Option<String>);
+
+    impl PhoenixTarget for Named {
+        fn subscribe(&mut self) {
+            // `Phoenix` keeps `self` at this address until `unsubscribe`, and the test only looks
+            // it up while its thread is parked, well before `unsubscribe` borrows it mutably
+            unsafe { BY_ID.register(self) }
+        }
+
+        fn unsubscribe(&mut self) {
+            BY_ID.deregister(self);
+        }
+    }
+
+    phoenix_tls! {
+        static NAMED: Named = Named(thread::current().name().map(String::from));
+    }
+
+    #[test]
+    fn thread_id_registry_finds_a_live_thread() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (exit_tx, exit_rx) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("named".into())
+            .spawn(move || {
+                NAMED.init();
+                ready_tx.send(thread::current().id()).unwrap();
+                let _ = exit_rx.recv();
+            })
+            .unwrap();
+
+        let id = ready_rx.recv().unwrap();
+        let name = BY_ID.with(id, |named| named.0.clone());
+        assert_eq!(name, Some(Some("named".into())));
+
+        drop(exit_tx);
+        thread.join().unwrap();
+        assert_eq!(BY_ID.with(id, |_| ()), None);
+    }
+
+    #[test]
+    fn thread_registry_lists_each_live_thread() {
+        let main_id = THREAD.handle().id;
+        assert_eq!(main_id, THREAD.handle().id);
+        assert_eq!(THREAD_LIST.len(), 1);
+
+        let other_id = thread::spawn(move || {
+            let this_id = THREAD.handle().id;
+            assert_eq!(this_id, THREAD.handle().id);
+            assert_eq!(THREAD_LIST.len(), 2);
+
+            let mut ids = Vec::new();
+            THREAD_LIST.for_each(|thread| ids.push(thread.id));
+            ids.sort_unstable();
+            assert_eq!(ids, [main_id.min(this_id), main_id.max(this_id)]);
+            this_id
+        })
+        .join()
+        .unwrap();
+        assert_ne!(main_id, other_id);
+        assert_eq!(THREAD_LIST.len(), 1);
+    }
+}
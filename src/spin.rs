@@ -0,0 +1,140 @@
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    hint,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::atomic::{
+        AtomicBool,
+        Ordering::{Acquire, Relaxed, Release},
+    },
+};
+
+/// A `ThreadRegistry` for `no_std`, locked with a spinlock instead of a `Mutex`.
+///
+/// Waiting for the lock busy-waits, burning CPU for as long as another thread holds it. That's
+/// cheap for the short critical sections here, but a thread that is preempted while holding the
+/// lock stalls everyone else until it is scheduled again, and an interrupt handler that takes the
+/// lock on the core already holding it spins forever. Prefer `ThreadRegistry` when `std` is
+/// available.
+pub struct SpinRegistry<T> {
+    locked: AtomicBool,
+    list:   UnsafeCell<Vec<NonNull<T>>>,
+}
+
+// The registry only ever hands out `&T`s, potentially to other threads.
+unsafe impl<T: Sync> Send for SpinRegistry<T> {}
+unsafe impl<T: Sync> Sync for SpinRegistry<T> {}
+
+impl<T> Default for SpinRegistry<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SpinRegistry<T> {
+    /// Creates an empty registry.
+    #[inline]
+    pub const fn new() -> Self {
+        SpinRegistry {
+            locked: AtomicBool::new(false),
+            list:   UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    /// Adds `value` to the registry.
+    ///
+    /// # Safety
+    ///
+    /// `value` must stay alive at its current address until it is passed to `deregister`, and must
+    /// not be borrowed mutably in the meantime, as `for_each` may be reading it on another thread.
+    /// That rules out deregistering a phoenix value from `unsubscribe`, which takes `&mut self`.
+    #[inline]
+    pub unsafe fn register(&self, value: &T) {
+        self.lock().push(NonNull::from(value))
+    }
+
+    /// Removes `value` from the registry, returning `false` if it was never registered.
+    #[inline]
+    pub fn deregister(&self, value: &T) -> bool {
+        let ptr = NonNull::from(value);
+        let mut list = self.lock();
+        match list.iter().position(|x| *x == ptr) {
+            Some(position) => {
+                list.swap_remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `f` on every registered value.
+    ///
+    /// The registry is locked for the duration, so no value can be deregistered (and therefore
+    /// destroyed) while `f` is running. `f` must not register or deregister values itself, as that
+    /// would spin forever.
+    #[inline]
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        for ptr in self.lock().iter() {
+            // registered values are alive until they are deregistered, which requires the lock
+            f(unsafe { ptr.as_ref() })
+        }
+    }
+
+    /// Returns the number of registered values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if no values are registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    #[inline]
+    fn lock(&self) -> SpinGuard<'_, T> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            // wait for the lock to look free before retrying the write
+            while self.locked.load(Relaxed) {
+                hint::spin_loop()
+            }
+        }
+        SpinGuard { registry: self }
+    }
+}
+
+struct SpinGuard<'a, T> {
+    registry: &'a SpinRegistry<T>,
+}
+
+impl<T> Deref for SpinGuard<'_, T> {
+    type Target = Vec<NonNull<T>>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // the lock is held
+        unsafe { &*self.registry.list.get() }
+    }
+}
+
+impl<T> DerefMut for SpinGuard<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // the lock is held, and `&mut self` rules out other borrows through this guard
+        unsafe { &mut *self.registry.list.get() }
+    }
+}
+
+impl<T> Drop for SpinGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.registry.locked.store(false, Release)
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    allocate, catch_in_dealloc, deallocate, debug_assert_not_iterating, next_index, on_subscribed,
+    refcount_overflow, unsubscribe_value, FreeOnUnwind, PhoenixTarget,
+};
+use alloc::alloc::{handle_alloc_error, Layout};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::Deref,
+    pin::Pin,
+    ptr::{self, NonNull},
+    sync::atomic::Ordering::{Acquire, Relaxed, Release},
+};
+
+cfg_if::cfg_if! {
+    if #[cfg(loom)] {
+        use loom::sync::atomic::{fence, AtomicUsize};
+    } else {
+        use core::sync::atomic::{fence, AtomicUsize};
+    }
+}
+
+// Same limit as `Arc`: well before an overflow, as other threads may clone concurrently.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+#[derive(Debug)]
+#[repr(C)]
+struct SyncPhoenixImpl<T> {
+    value:     ManuallyDrop<T>,
+    ref_count: AtomicUsize,
+}
+
+/// An atomically reference counted variant of `Phoenix` that can be sent between threads.
+///
+/// `subscribe` is called on creation, and `unsubscribe` on whichever thread drops the last handle.
+/// Every other `PhoenixTarget` hook applies just as it does to `Phoenix`, including `allocator` and
+/// `live_registry`.
+pub struct SyncPhoenix<T: 'static + PhoenixTarget> {
+    raw:     NonNull<SyncPhoenixImpl<T>>,
+    phantom: PhantomData<SyncPhoenixImpl<T>>,
+}
+
+unsafe impl<T: 'static + PhoenixTarget + Send + Sync> Send for SyncPhoenix<T> {}
+unsafe impl<T: 'static + PhoenixTarget + Send + Sync> Sync for SyncPhoenix<T> {}
+
+impl<T: 'static + PhoenixTarget> Clone for SyncPhoenix<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let count = self.as_ref().ref_count.fetch_add(1, Relaxed);
+        refcount_assert!(count > 0, "attempt to clone a deallocated `SyncPhoenix`");
+
+        // We must check for overflow because users can mem::forget(x.clone())
+        // repeatedly.
+        if nudge::unlikely(count > MAX_REFCOUNT) {
+            if cfg!(feature = "overflow-panic") {
+                // other threads may have incremented in the meantime, but each one backs its
+                // increment out again, so the count stays far from wrapping
+                self.as_ref().ref_count.fetch_sub(1, Relaxed);
+            }
+            refcount_overflow()
+        }
+
+        SyncPhoenix {
+            raw:     self.raw,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Drop for SyncPhoenix<T> {
+    #[inline]
+    fn drop(&mut self) {
+        let count = self.as_ref().ref_count.fetch_sub(1, Release);
+        refcount_assert!(count > 0, "double free on `SyncPhoenix` attempted");
+
+        if nudge::unlikely(count == 1) {
+            // synchronize with the `Release` decrements on other threads before destroying
+            fence(Acquire);
+            debug_assert_not_iterating::<T>();
+
+            // this is safe as long as the reference counting logic is safe
+            unsafe {
+                dealloc::<_>(self.raw);
+            }
+
+            #[inline(never)]
+            #[cold]
+            unsafe extern "C" fn dealloc<T: 'static + PhoenixTarget>(
+                this_ptr: NonNull<SyncPhoenixImpl<T>>,
+            ) {
+                let value = ptr::addr_of_mut!((*this_ptr.as_ptr()).value);
+                unsubscribe_value(value.cast::<T>(), |value| {
+                    catch_in_dealloc(|| value.unsubscribe())
+                });
+                let value = &mut *value;
+                // the value is dropped in place, so it never moves again
+                catch_in_dealloc(|| T::on_drop(Pin::new_unchecked(value)));
+                catch_in_dealloc(|| ManuallyDrop::drop(value));
+                deallocate::<T, _>(this_ptr);
+            }
+        }
+    }
+}
+
+impl<T: 'static + PhoenixTarget> SyncPhoenix<T> {
+    /// Creates a new `SyncPhoenix` holding `T::default()`, calling `subscribe` on it.
+    #[cold]
+    pub fn new() -> Self
+    where
+        T: Default,
+    {
+        Self::from_value(T::default())
+    }
+
+    /// Creates a new `SyncPhoenix` holding `value`, calling `subscribe` on it.
+    #[cold]
+    pub fn from_value(value: T) -> Self {
+        debug_assert_not_iterating::<T>();
+        let index = next_index();
+        let layout = Layout::new::<SyncPhoenixImpl<T>>();
+        let raw = NonNull::new(unsafe { allocate::<T, SyncPhoenixImpl<T>>() })
+            .unwrap_or_else(|| handle_alloc_error(layout));
+        unsafe {
+            raw.as_ptr().write(SyncPhoenixImpl {
+                value:     ManuallyDrop::new(value),
+                ref_count: AtomicUsize::new(1),
+            });
+            let value = &mut (*raw.as_ptr()).value;
+            let free_on_unwind = FreeOnUnwind::<T, _>(raw, PhantomData);
+            value.subscribe_with_index(index);
+            mem::forget(free_on_unwind);
+            on_subscribed(&**value);
+        }
+        SyncPhoenix {
+            raw,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of `SyncPhoenix` handles to this allocation.
+    ///
+    /// This is only a snapshot. Other threads may clone or drop handles at any time.
+    #[inline]
+    pub fn strong_count(&self) -> usize {
+        self.as_ref().ref_count.load(Relaxed)
+    }
+
+    /// Returns `true` if both `SyncPhoenix`es point to the same allocation.
+    #[inline]
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        a.raw == b.raw
+    }
+
+    #[inline]
+    fn as_ref(&self) -> &SyncPhoenixImpl<T> {
+        // this is safe as long as the reference counting logic is safe
+        unsafe { self.raw.as_ref() }
+    }
+}
+
+impl<T: 'static + PhoenixTarget + Default> Default for SyncPhoenix<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static + PhoenixTarget + fmt::Debug> fmt::Debug for SyncPhoenix<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncPhoenix")
+            .field("value", &**self)
+            .field("strong_count", &self.strong_count())
+            .finish()
+    }
+}
+
+impl<T: 'static + PhoenixTarget> Deref for SyncPhoenix<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.as_ref().value
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{PhoenixTarget, SyncPhoenix};
+use core::future::Future;
+use tokio::task::{futures::TaskLocalFuture, LocalKey};
+
+/// A task local phoenix, declared with `task_phoenix!`.
+///
+/// Thread locals don't map to tasks on work-stealing runtimes, which may poll a task on a
+/// different thread every time. A `TaskPhoenixKey` instead holds one value per `scope`, which
+/// follows the task from thread to thread, and so is a `SyncPhoenix`.
+pub struct TaskPhoenixKey<T: PhoenixTarget + 'static> {
+    // a function rather than a reference, since consts can only refer to statics from Rust 1.83
+    #[doc(hidden)]
+    pub __key: fn() -> &'static LocalKey<SyncPhoenix<T>>,
+}
+
+impl<T: PhoenixTarget + 'static> Clone for TaskPhoenixKey<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: PhoenixTarget + 'static> Copy for TaskPhoenixKey<T> {}
+
+impl<T: PhoenixTarget + 'static> TaskPhoenixKey<T> {
+    /// Runs `f` with `value` as the task local.
+    ///
+    /// `value` is subscribed immediately, and unsubscribed once the returned future is dropped
+    /// and any handles from `handle` are gone.
+    #[inline]
+    pub fn scope<F: Future>(self, value: T, f: F) -> TaskLocalFuture<SyncPhoenix<T>, F> {
+        (self.__key)().scope(SyncPhoenix::from_value(value), f)
+    }
+
+    /// Returns a handle to the current task's value, which can be held across `.await`s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of `scope`.
+    #[inline]
+    pub fn handle(self) -> SyncPhoenix<T> {
+        (self.__key)().with(SyncPhoenix::clone)
+    }
+
+    /// Calls `f` with the current task's value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of `scope`.
+    #[inline]
+    pub fn with<F: FnOnce(&T) -> O, O>(self, f: F) -> O {
+        (self.__key)().with(|phoenix| f(phoenix))
+    }
+
+    /// Like `with`, but returns `None` if called outside of `scope`.
+    #[inline]
+    pub fn try_with<F: FnOnce(&T) -> O, O>(self, f: F) -> Option<O> {
+        (self.__key)().try_with(|phoenix| f(phoenix)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{task_phoenix, PhoenixCell};
+    use tokio::{runtime::Builder, task};
+
+    task_phoenix! {
+        static REQUEST: PhoenixCell<u32>;
+    }
+
+    #[test]
+    fn handle_lives_across_awaits_inside_scope() {
+        let runtime = Builder::new_current_thread().build().unwrap();
+        let handle = runtime.block_on(REQUEST.scope(PhoenixCell::new(1), async {
+            let handle = REQUEST.handle();
+            task::yield_now().await;
+            REQUEST.with(|request| request.set(2));
+            assert_eq!(handle.get(), 2);
+            handle
+        }));
+        assert_eq!(handle.get(), 2);
+        assert_eq!(handle.strong_count(), 1);
+        assert_eq!(REQUEST.try_with(|request| request.get()), None);
+    }
+}
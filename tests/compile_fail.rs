@@ -0,0 +1,19 @@
+// The expected diagnostics name private types, and rustc shortens paths against every crate in the
+// build, so they are only checked with features that don't pull in dependencies.
+#![cfg(all(
+    feature = "std",
+    not(any(
+        feature = "derive",
+        feature = "inventory",
+        feature = "serde",
+        feature = "tokio",
+        feature = "tracing",
+        feature = "nightly"
+    ))
+))]
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
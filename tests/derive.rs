@@ -0,0 +1,54 @@
+#![cfg(feature = "derive")]
+
+use phoenix_tls::{phoenix_tls, Phoenix, PhoenixTarget, ThreadRegistry};
+
+#[derive(PhoenixTarget, Default)]
+struct Plain(u32);
+
+static LIVE: ThreadRegistry<Registered> = ThreadRegistry::new();
+
+#[derive(PhoenixTarget, Default)]
+#[phoenix(registry = LIVE)]
+struct Registered(u32);
+
+mod reexport {
+    pub use phoenix_tls as tls;
+}
+
+#[derive(PhoenixTarget, Default)]
+#[phoenix(crate = reexport::tls)]
+struct Renamed;
+
+phoenix_tls! {
+    static PLAIN: Plain;
+    static REGISTERED: Registered = Registered(7);
+}
+
+#[test]
+fn derive_without_a_registry_registers_nothing() {
+    assert_eq!(PLAIN.with(|plain| plain.0), 0);
+    assert!(Plain::live_registry().is_none());
+    drop(Phoenix::<Renamed>::new());
+}
+
+#[test]
+fn derive_with_a_registry_lists_live_values() {
+    let handle = REGISTERED.handle();
+    assert_eq!(
+        Phoenix::collect_snapshots(|registered: &Registered| registered.0),
+        [7]
+    );
+    drop(handle);
+    assert_eq!(LIVE.len(), 1);
+
+    let value = Phoenix::try_unwrap(REGISTERED.take()).ok().unwrap();
+    assert_eq!(value.0, 7);
+    // `take` registered a fresh value, and unwrapping the old one deregistered it
+    assert_eq!(LIVE.len(), 1);
+}
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui_derive/*.rs");
+}
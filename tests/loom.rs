@@ -0,0 +1,123 @@
+//! Models of the thread safe parts of the crate, checked with loom.
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --test loom --release`.
+
+#![cfg(loom)]
+
+use loom::{
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    },
+    thread,
+};
+use phoenix_tls::{
+    IntrusiveNode, IntrusiveRegistry, Phoenix, PhoenixTarget, SyncPhoenix, ThreadRegistry,
+};
+
+struct Counted(Arc<AtomicUsize>);
+
+impl PhoenixTarget for Counted {
+    fn subscribe(&mut self) {}
+
+    fn unsubscribe(&mut self) {
+        self.0.fetch_add(1, Relaxed);
+    }
+}
+
+#[test]
+fn sync_phoenix_unsubscribes_once() {
+    loom::model(|| {
+        let unsubscribes = Arc::new(AtomicUsize::new(0));
+        let phoenix = SyncPhoenix::from_value(Counted(unsubscribes.clone()));
+
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let phoenix = phoenix.clone();
+                thread::spawn(move || drop(phoenix.clone()))
+            })
+            .collect();
+        drop(phoenix);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(unsubscribes.load(Relaxed), 1);
+    });
+}
+
+loom::lazy_static! {
+    static ref LIVE: ThreadRegistry<Live> = ThreadRegistry::new();
+    static ref LINKED: IntrusiveRegistry<Linked> = IntrusiveRegistry::new();
+}
+
+// Reads of a value after it is freed are caught by loom, through the atomic.
+struct Live(AtomicUsize);
+
+impl PhoenixTarget for Live {
+    fn subscribe(&mut self) {}
+
+    fn unsubscribe(&mut self) {}
+
+    fn live_registry() -> Option<&'static ThreadRegistry<Self>> {
+        Some(&LIVE)
+    }
+}
+
+#[test]
+fn thread_registry_for_each_races_thread_exit() {
+    loom::model(|| {
+        let thread = thread::spawn(|| {
+            // dropped as the thread exits, like a thread local
+            let phoenix = Phoenix::from_value(Live(AtomicUsize::new(7)));
+            drop(phoenix);
+        });
+
+        Phoenix::<Live>::for_each_live(|live| assert_eq!(live.0.load(Relaxed), 7));
+        thread.join().unwrap();
+
+        assert!(LIVE.is_empty());
+    });
+}
+
+struct Linked {
+    node:  IntrusiveNode<Linked>,
+    value: AtomicUsize,
+}
+
+impl PhoenixTarget for Linked {
+    fn subscribe(&mut self) {
+        // `Phoenix` keeps `self`, and the node inside it, at this address until `unsubscribe`
+        unsafe { LINKED.register(self, &self.node) }
+    }
+
+    fn unsubscribe(&mut self) {
+        assert!(LINKED.deregister(&self.node));
+    }
+}
+
+#[test]
+fn intrusive_registry_for_each_races_thread_exit() {
+    loom::model(|| {
+        let threads: Vec<_> = (0..2)
+            .map(|i| {
+                thread::spawn(move || {
+                    let phoenix = Phoenix::from_value(Linked {
+                        node:  IntrusiveNode::new(),
+                        value: AtomicUsize::new(i),
+                    });
+                    drop(phoenix);
+                })
+            })
+            .collect();
+
+        LINKED.for_each(|linked| assert!(linked.value.load(Relaxed) < 2));
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut count = 0;
+        LINKED.for_each(|_| count += 1);
+        assert_eq!(count, 0);
+    });
+}
@@ -0,0 +1,46 @@
+//! `resurrection_count` is shared by every test in a process, so it gets a test binary of its own.
+
+#![cfg(feature = "std")]
+
+use phoenix_tls::{phoenix_tls, resurrection_count, PhoenixCell, PhoenixKey, ResurrectionPolicy};
+use std::thread;
+
+phoenix_tls! {
+    static RAW: PhoenixCell<u32>;
+}
+
+const COUNTER: PhoenixKey<PhoenixCell<u32>> = RAW.with_resurrection(ResurrectionPolicy::Temporary);
+
+struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        COUNTER.with(|counter| counter.set(1))
+    }
+}
+
+thread_local! {
+    static GUARD: Guard = const { Guard };
+}
+
+#[test]
+fn resurrection_count_only_counts_accesses_after_destruction() {
+    thread::spawn(|| {
+        COUNTER.with(|counter| counter.set(1));
+        drop(COUNTER.handle());
+        drop(COUNTER.reset());
+        COUNTER.init();
+    })
+    .join()
+    .unwrap();
+    assert_eq!(resurrection_count(), 0);
+
+    // the guard is registered first, so on most platforms it is destroyed after `COUNTER`
+    thread::spawn(|| {
+        GUARD.with(|_| ());
+        COUNTER.init();
+    })
+    .join()
+    .unwrap();
+    assert!(resurrection_count() > 0);
+}
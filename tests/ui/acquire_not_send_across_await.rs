@@ -0,0 +1,18 @@
+// A handle held across an `.await` makes the future `!Send`, so work-stealing runtimes reject it.
+use phoenix_tls::{phoenix_tls, PhoenixCell};
+
+phoenix_tls! {
+    static COUNTER: PhoenixCell<u32>;
+}
+
+fn require_send<F: Send>(_: F) {}
+
+async fn yield_now() {}
+
+fn main() {
+    require_send(async {
+        let counter = COUNTER.acquire();
+        yield_now().await;
+        counter.set(1);
+    });
+}
@@ -0,0 +1,12 @@
+// `Phoenix` is covariant, so the subtyping only goes the other way, which is checked inside the
+// crate.
+use phoenix_tls::{NoSubscribe, Phoenix};
+
+type Sub = NoSubscribe<for<'a> fn(&'a ())>;
+type Super = NoSubscribe<fn(&'static ())>;
+
+fn phoenix(x: Phoenix<Super>) -> Phoenix<Sub> {
+    x
+}
+
+fn main() {}
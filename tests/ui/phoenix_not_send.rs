@@ -0,0 +1,7 @@
+use phoenix_tls::{NoSubscribe, Phoenix};
+
+fn require_send<T: Send>(_: T) {}
+
+fn main() {
+    require_send(Phoenix::from_value(NoSubscribe(())));
+}
@@ -0,0 +1,7 @@
+use phoenix_tls::{NoSubscribe, Phoenix};
+
+fn require_sync<T: Sync>(_: &T) {}
+
+fn main() {
+    require_sync(&Phoenix::from_value(NoSubscribe(())));
+}
@@ -0,0 +1,12 @@
+// `WeakPhoenix` is covariant, so the subtyping only goes the other way, which is checked inside
+// the crate.
+use phoenix_tls::{NoSubscribe, WeakPhoenix};
+
+type Sub = NoSubscribe<for<'a> fn(&'a ())>;
+type Super = NoSubscribe<fn(&'static ())>;
+
+fn weak(x: WeakPhoenix<Super>) -> WeakPhoenix<Sub> {
+    x
+}
+
+fn main() {}
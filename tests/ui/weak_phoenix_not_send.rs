@@ -0,0 +1,8 @@
+use phoenix_tls::{NoSubscribe, Phoenix};
+
+fn require_send<T: Send>(_: T) {}
+
+fn main() {
+    let phoenix = Phoenix::from_value(NoSubscribe(()));
+    require_send(phoenix.downgrade());
+}
@@ -0,0 +1,11 @@
+use phoenix_tls::PhoenixTarget;
+
+#[derive(PhoenixTarget)]
+#[phoenix(registy = LIVE)]
+struct Misspelled;
+
+#[derive(PhoenixTarget)]
+#[phoenix(crate = phoenix_tls, crate = phoenix_tls)]
+struct Duplicate;
+
+fn main() {}